@@ -0,0 +1,92 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use tracing_fluent_assertions::{Assertion, AssertionRegistry};
+
+const ASSERTIONS: usize = 1000;
+
+/// Counts allocations, including reallocations, as growing the registry's storage is exactly what
+/// reserving capacity up front is meant to avoid.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn register_all(registry: &AssertionRegistry, names: &[String]) -> Vec<Assertion> {
+    names
+        .iter()
+        .map(|name| {
+            registry
+                .build()
+                .with_name(name.as_str())
+                .was_created()
+                .finalize()
+        })
+        .collect()
+}
+
+/// Counts the allocations made while registering every assertion in `registry`.
+fn count_allocations(registry: AssertionRegistry, names: &[String]) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let assertions = register_all(&registry, names);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(assertions);
+    allocations
+}
+
+fn registration(c: &mut Criterion) {
+    let names = (0..ASSERTIONS)
+        .map(|i| format!("span_{}", i))
+        .collect::<Vec<_>>();
+
+    // Criterion only measures time, so the allocation counts are reported alongside it.
+    println!(
+        "register_1000_assertions/default: {} allocations",
+        count_allocations(AssertionRegistry::default(), &names)
+    );
+    println!(
+        "register_1000_assertions/with_capacity: {} allocations",
+        count_allocations(AssertionRegistry::with_capacity(ASSERTIONS), &names)
+    );
+
+    let mut group = c.benchmark_group("register_1000_assertions");
+    group.bench_function("default", |b| {
+        b.iter_batched(
+            AssertionRegistry::default,
+            |registry| black_box(register_all(&registry, &names)),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("with_capacity", |b| {
+        b.iter_batched(
+            || AssertionRegistry::with_capacity(ASSERTIONS),
+            |registry| black_box(register_all(&registry, &names)),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, registration);
+criterion_main!(benches);
@@ -1,12 +1,79 @@
 //! Core assertion types and utilities.
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, RangeInclusive},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tracing::{Id, Level};
 
 use crate::{
-    matcher::SpanMatcher,
-    state::{EntryState, State},
+    matcher::{SpanInfo, SpanMatcher},
+    state::{EntryState, Registration, State},
+    value::{FieldBound, FieldType, FieldValueSnapshot},
 };
 
-enum AssertionCriterion {
+/// A stage in the lifecycle of a span.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Stage {
+    /// The span was created.
+    Created,
+
+    /// The span was entered.
+    Entered,
+
+    /// The span was exited.
+    Exited,
+
+    /// The span was closed.
+    Closed,
+}
+
+impl Stage {
+    fn count(&self, counts: &EntryCounts) -> usize {
+        match self {
+            Stage::Created => counts.created,
+            Stage::Entered => counts.entered,
+            Stage::Exited => counts.exited,
+            Stage::Closed => counts.closed,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Created => write!(f, "created"),
+            Stage::Entered => write!(f, "entered"),
+            Stage::Exited => write!(f, "exited"),
+            Stage::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+fn describe_stages(stages: &[Stage]) -> String {
+    let stages = stages
+        .iter()
+        .map(|stage| stage.to_string())
+        .collect::<Vec<_>>();
+    format!("[{}]", stages.join(", "))
+}
+
+fn ordering_symbol(ordering: &Ordering) -> &'static str {
+    match ordering {
+        Ordering::Less => "<",
+        Ordering::Equal => "==",
+        Ordering::Greater => ">",
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum AssertionCriterion {
     WasCreated,
     WasEntered,
     WasExited,
@@ -23,30 +90,29 @@ enum AssertionCriterion {
     EnteredAtLeast(usize),
     ExitedAtLeast(usize),
     ClosedAtLeast(usize),
+    DistinctEnteredExactly(usize),
+    EveryInstanceEntered,
+    EveryInstanceEnteredAtMostOnce,
+    NeverReentered,
+    LifecycleSequence(Vec<Stage>),
+    WasCurrent,
+    NeverOverExited,
+    CurrentlyOpen,
+    CurrentlyClosed,
+    Relation(Stage, Ordering, Stage),
+    HadNoChildren,
+    EnteredOnAtLeastThreads(usize),
+    MaxDepthAtMost(usize),
+    DepthExactly(usize),
+    EmittedEventAtLeast(usize),
+    EmittedEventWithLevel(Level),
+    FieldRecordedAtLeast(String, usize),
+    TotalDurationAtMost(Duration),
+    TotalDurationAtLeast(Duration),
+    BusyDurationAtMost(Duration),
 }
 
 impl AssertionCriterion {
-    pub fn assert(&self, state: &Arc<EntryState>) {
-        match self {
-            AssertionCriterion::WasCreated => assert!(state.num_created() != 0),
-            AssertionCriterion::WasEntered => assert!(state.num_entered() != 0),
-            AssertionCriterion::WasExited => assert!(state.num_exited() != 0),
-            AssertionCriterion::WasClosed => assert!(state.num_closed() != 0),
-            AssertionCriterion::WasNotCreated => assert_eq!(0, state.num_created()),
-            AssertionCriterion::WasNotEntered => assert_eq!(0, state.num_entered()),
-            AssertionCriterion::WasNotExited => assert_eq!(0, state.num_exited()),
-            AssertionCriterion::WasNotClosed => assert_eq!(0, state.num_closed()),
-            AssertionCriterion::CreatedExactly(times) => assert_eq!(state.num_created(), *times),
-            AssertionCriterion::EnteredExactly(times) => assert_eq!(state.num_entered(), *times),
-            AssertionCriterion::ExitedExactly(times) => assert_eq!(state.num_exited(), *times),
-            AssertionCriterion::ClosedExactly(times) => assert_eq!(state.num_closed(), *times),
-            AssertionCriterion::CreatedAtLeast(times) => assert!(state.num_created() >= *times),
-            AssertionCriterion::EnteredAtLeast(times) => assert!(state.num_entered() >= *times),
-            AssertionCriterion::ExitedAtLeast(times) => assert!(state.num_exited() >= *times),
-            AssertionCriterion::ClosedAtLeast(times) => assert!(state.num_closed() >= *times),
-        }
-    }
-
     pub fn try_assert(&self, state: &Arc<EntryState>) -> bool {
         match self {
             AssertionCriterion::WasCreated => state.num_created() != 0,
@@ -65,10 +131,310 @@ impl AssertionCriterion {
             AssertionCriterion::EnteredAtLeast(times) => state.num_entered() >= *times,
             AssertionCriterion::ExitedAtLeast(times) => state.num_exited() >= *times,
             AssertionCriterion::ClosedAtLeast(times) => state.num_closed() >= *times,
+            AssertionCriterion::DistinctEnteredExactly(n) => state.distinct_entered() == *n,
+            AssertionCriterion::EveryInstanceEntered => state.num_never_entered() == 0,
+            AssertionCriterion::EveryInstanceEnteredAtMostOnce => {
+                state.num_entered_more_than_once() == 0
+            }
+            AssertionCriterion::NeverReentered => state.num_reentered() == 0,
+            AssertionCriterion::LifecycleSequence(expected) => {
+                state.has_lifecycle_sequence(expected)
+            }
+            AssertionCriterion::WasCurrent => state.num_current() != 0,
+            AssertionCriterion::NeverOverExited => !state.was_over_exited(),
+            AssertionCriterion::CurrentlyOpen => {
+                let counts = state.snapshot();
+                counts.entered > counts.exited
+            }
+            AssertionCriterion::CurrentlyClosed => {
+                let counts = state.snapshot();
+                counts.entered <= counts.exited
+            }
+            AssertionCriterion::Relation(lhs, ordering, rhs) => {
+                let counts = state.snapshot();
+                lhs.count(&counts).cmp(&rhs.count(&counts)) == *ordering
+            }
+            AssertionCriterion::HadNoChildren => state.num_parents() == 0,
+            AssertionCriterion::EnteredOnAtLeastThreads(n) => state.distinct_threads() >= *n,
+            AssertionCriterion::MaxDepthAtMost(n) => state.max_depth() <= *n,
+            AssertionCriterion::DepthExactly(n) => match state.depth_range() {
+                Some((min, max)) => min == *n && max == *n,
+                None => true,
+            },
+            AssertionCriterion::EmittedEventAtLeast(times) => state.num_events() >= *times,
+            AssertionCriterion::EmittedEventWithLevel(level) => {
+                state.num_events_with_level(level) != 0
+            }
+            AssertionCriterion::FieldRecordedAtLeast(field, times) => {
+                state.num_recorded(field) >= *times
+            }
+            AssertionCriterion::TotalDurationAtMost(duration) => {
+                state.total_duration() <= *duration
+            }
+            AssertionCriterion::TotalDurationAtLeast(duration) => {
+                state.total_duration() >= *duration
+            }
+            AssertionCriterion::BusyDurationAtMost(duration) => state.total_duration() <= *duration,
+        }
+    }
+
+    /// Describes how the criterion was not met, in terms of the span which failed to meet it.
+    pub fn describe_failure(&self, state: &Arc<EntryState>) -> String {
+        match self {
+            AssertionCriterion::WasCreated => "was never created".to_string(),
+            AssertionCriterion::WasEntered => "was never entered".to_string(),
+            AssertionCriterion::WasExited => "was never exited".to_string(),
+            AssertionCriterion::WasClosed => "was never closed".to_string(),
+            AssertionCriterion::WasNotCreated => {
+                let mut failure =
+                    format!("was created {}, expected never", times(state.num_created()));
+                if let Some(offender) = state.first_created_span() {
+                    failure.push_str(&format!(", first created: {}", offender));
+                }
+                failure
+            }
+            AssertionCriterion::WasNotEntered => {
+                let mut failure =
+                    format!("was entered {}, expected never", times(state.num_entered()));
+                if let Some(offender) = state.first_entered_span() {
+                    failure.push_str(&format!(", first entered: {}", offender));
+                }
+                failure
+            }
+            AssertionCriterion::WasNotExited => {
+                format!("was exited {}, expected never", times(state.num_exited()))
+            }
+            AssertionCriterion::WasNotClosed => {
+                format!("was closed {}, expected never", times(state.num_closed()))
+            }
+            AssertionCriterion::CreatedExactly(n) => format!(
+                "was created {}, expected exactly {}",
+                times(state.num_created()),
+                times(*n)
+            ),
+            AssertionCriterion::EnteredExactly(n) => format!(
+                "was entered {}, expected exactly {}",
+                times(state.num_entered()),
+                times(*n)
+            ),
+            AssertionCriterion::ExitedExactly(n) => format!(
+                "was exited {}, expected exactly {}",
+                times(state.num_exited()),
+                times(*n)
+            ),
+            AssertionCriterion::ClosedExactly(n) => format!(
+                "was closed {}, expected exactly {}",
+                times(state.num_closed()),
+                times(*n)
+            ),
+            AssertionCriterion::CreatedAtLeast(n) => format!(
+                "was created {}, expected at least {}",
+                times(state.num_created()),
+                times(*n)
+            ),
+            AssertionCriterion::EnteredAtLeast(n) => format!(
+                "was entered {}, expected at least {}",
+                times(state.num_entered()),
+                times(*n)
+            ),
+            AssertionCriterion::ExitedAtLeast(n) => format!(
+                "was exited {}, expected at least {}",
+                times(state.num_exited()),
+                times(*n)
+            ),
+            AssertionCriterion::ClosedAtLeast(n) => format!(
+                "was closed {}, expected at least {}",
+                times(state.num_closed()),
+                times(*n)
+            ),
+            AssertionCriterion::DistinctEnteredExactly(n) => format!(
+                "had {} distinct instance(s) entered, expected exactly {}",
+                state.distinct_entered(),
+                n
+            ),
+            AssertionCriterion::EveryInstanceEntered => format!(
+                "had {} instance(s) created but never entered, expected none",
+                state.num_never_entered()
+            ),
+            AssertionCriterion::EveryInstanceEnteredAtMostOnce => format!(
+                "had {} instance(s) entered more than once, expected none",
+                state.num_entered_more_than_once()
+            ),
+            AssertionCriterion::NeverReentered => format!(
+                "had {} instance(s) entered again while already entered, expected none",
+                state.num_reentered()
+            ),
+            AssertionCriterion::LifecycleSequence(expected) => format!(
+                "had no instance with the lifecycle sequence {}",
+                describe_stages(expected)
+            ),
+            AssertionCriterion::WasCurrent => "was never the current span".to_string(),
+            AssertionCriterion::NeverOverExited => {
+                "was at some point exited more times than it was entered".to_string()
+            }
+            AssertionCriterion::CurrentlyOpen => {
+                let counts = state.snapshot();
+                format!(
+                    "was entered {} and exited {}, expected to currently be open",
+                    times(counts.entered),
+                    times(counts.exited)
+                )
+            }
+            AssertionCriterion::CurrentlyClosed => {
+                let counts = state.snapshot();
+                format!(
+                    "was entered {} and exited {}, expected to currently be closed",
+                    times(counts.entered),
+                    times(counts.exited)
+                )
+            }
+            AssertionCriterion::Relation(lhs, ordering, rhs) => {
+                let counts = state.snapshot();
+                format!(
+                    "was {} {} and {} {}, expected {} {} {}",
+                    lhs,
+                    times(lhs.count(&counts)),
+                    rhs,
+                    times(rhs.count(&counts)),
+                    lhs,
+                    ordering_symbol(ordering),
+                    rhs
+                )
+            }
+            AssertionCriterion::HadNoChildren => format!(
+                "had child spans in {} instance(s), expected none",
+                state.num_parents()
+            ),
+            AssertionCriterion::EnteredOnAtLeastThreads(n) => format!(
+                "was entered on {} thread(s), expected at least {}",
+                state.distinct_threads(),
+                n
+            ),
+            AssertionCriterion::MaxDepthAtMost(n) => format!(
+                "was nested under {} parent span(s), expected at most {}",
+                state.max_depth(),
+                n
+            ),
+            AssertionCriterion::DepthExactly(n) => {
+                let (min, max) = state.depth_range().unwrap_or_default();
+                if min == max {
+                    format!(
+                        "was nested under {} parent span(s), expected exactly {}",
+                        min, n
+                    )
+                } else {
+                    format!(
+                        "was nested under between {} and {} parent span(s), expected exactly {}",
+                        min, max, n
+                    )
+                }
+            }
+            AssertionCriterion::EmittedEventAtLeast(n) => format!(
+                "had {} event(s) emitted within it, expected at least {}",
+                state.num_events(),
+                n
+            ),
+            AssertionCriterion::EmittedEventWithLevel(level) => {
+                format!("never had a {} event emitted within it", level)
+            }
+            AssertionCriterion::FieldRecordedAtLeast(field, n) => format!(
+                "had field '{}' recorded {}, expected at least {}",
+                field,
+                times(state.num_recorded(field)),
+                times(*n)
+            ),
+            AssertionCriterion::TotalDurationAtMost(duration) => format!(
+                "was active for {:?}, expected at most {:?}",
+                state.total_duration(),
+                duration
+            ),
+            AssertionCriterion::TotalDurationAtLeast(duration) => format!(
+                "was active for {:?}, expected at least {:?}",
+                state.total_duration(),
+                duration
+            ),
+            AssertionCriterion::BusyDurationAtMost(duration) => format!(
+                "was busy for {:?}, expected at most {:?}",
+                state.total_duration(),
+                duration
+            ),
+        }
+    }
+}
+
+impl fmt::Display for AssertionCriterion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssertionCriterion::WasCreated => write!(f, "was_created"),
+            AssertionCriterion::WasEntered => write!(f, "was_entered"),
+            AssertionCriterion::WasExited => write!(f, "was_exited"),
+            AssertionCriterion::WasClosed => write!(f, "was_closed"),
+            AssertionCriterion::WasNotCreated => write!(f, "was_not_created"),
+            AssertionCriterion::WasNotEntered => write!(f, "was_not_entered"),
+            AssertionCriterion::WasNotExited => write!(f, "was_not_exited"),
+            AssertionCriterion::WasNotClosed => write!(f, "was_not_closed"),
+            AssertionCriterion::CreatedExactly(n) => write!(f, "was_created_exactly({})", n),
+            AssertionCriterion::EnteredExactly(n) => write!(f, "was_entered_exactly({})", n),
+            AssertionCriterion::ExitedExactly(n) => write!(f, "was_exited_exactly({})", n),
+            AssertionCriterion::ClosedExactly(n) => write!(f, "was_closed_exactly({})", n),
+            AssertionCriterion::CreatedAtLeast(n) => write!(f, "was_created_at_least({})", n),
+            AssertionCriterion::EnteredAtLeast(n) => write!(f, "was_entered_at_least({})", n),
+            AssertionCriterion::ExitedAtLeast(n) => write!(f, "was_exited_at_least({})", n),
+            AssertionCriterion::ClosedAtLeast(n) => write!(f, "was_closed_at_least({})", n),
+            AssertionCriterion::DistinctEnteredExactly(n) => {
+                write!(f, "distinct_entered_exactly({})", n)
+            }
+            AssertionCriterion::EveryInstanceEntered => write!(f, "every_instance_entered"),
+            AssertionCriterion::EveryInstanceEnteredAtMostOnce => {
+                write!(f, "every_instance_entered_at_most_once")
+            }
+            AssertionCriterion::NeverReentered => write!(f, "was_never_reentered"),
+            AssertionCriterion::LifecycleSequence(expected) => {
+                write!(f, "had_lifecycle_sequence({})", describe_stages(expected))
+            }
+            AssertionCriterion::WasCurrent => write!(f, "was_current"),
+            AssertionCriterion::NeverOverExited => write!(f, "was_never_over_exited"),
+            AssertionCriterion::CurrentlyOpen => write!(f, "is_currently_open"),
+            AssertionCriterion::CurrentlyClosed => write!(f, "is_currently_closed"),
+            AssertionCriterion::Relation(lhs, ordering, rhs) => {
+                write!(f, "relation({} {} {})", lhs, ordering_symbol(ordering), rhs)
+            }
+            AssertionCriterion::HadNoChildren => write!(f, "had_no_children"),
+            AssertionCriterion::EnteredOnAtLeastThreads(n) => {
+                write!(f, "entered_on_at_least_n_threads({})", n)
+            }
+            AssertionCriterion::MaxDepthAtMost(n) => write!(f, "max_depth_at_most({})", n),
+            AssertionCriterion::DepthExactly(n) => write!(f, "had_depth_exactly({})", n),
+            AssertionCriterion::EmittedEventAtLeast(n) => {
+                write!(f, "emitted_event_at_least({})", n)
+            }
+            AssertionCriterion::EmittedEventWithLevel(level) => {
+                write!(f, "emitted_event_with_level({})", level)
+            }
+            AssertionCriterion::FieldRecordedAtLeast(field, n) => {
+                write!(f, "field_recorded_at_least({}, {})", field, n)
+            }
+            AssertionCriterion::TotalDurationAtMost(duration) => {
+                write!(f, "total_duration_at_most({:?})", duration)
+            }
+            AssertionCriterion::TotalDurationAtLeast(duration) => {
+                write!(f, "total_duration_at_least({:?})", duration)
+            }
+            AssertionCriterion::BusyDurationAtMost(duration) => {
+                write!(f, "busy_duration_at_most({:?})", duration)
+            }
         }
     }
 }
 
+fn times(n: usize) -> String {
+    if n == 1 {
+        "1 time".to_string()
+    } else {
+        format!("{} times", n)
+    }
+}
+
 /// A specific set of criteria to enforce on matching spans.
 ///
 /// Assertions represent both a span "matcher" -- which controls which spans the criteria are
@@ -88,23 +454,71 @@ pub struct Assertion {
     state: Arc<State>,
     entry_state: Arc<EntryState>,
     matcher: SpanMatcher,
-    criteria: Vec<AssertionCriterion>,
+    criteria: Arc<Vec<AssertionCriterion>>,
+    label: Option<String>,
+    id: u64,
+    window: Mutex<Option<Window>>,
+}
+
+/// The counts at the start, and potentially the end, of an assertion's window.
+struct Window {
+    start: EntryCounts,
+    stop: Option<EntryCounts>,
 }
 
 impl Assertion {
     /// Asserts that all criteria have been met.
     ///
-    /// Uses the "assert" macros from the standard library, so criterion which have not been met
-    /// will cause a panic, similar to using the "assert" macros directly.
+    /// Criterion which have not been met will cause a panic, similar to using the "assert" macros
+    /// from the standard library directly.  The panic message describes the span matcher and how
+    /// each criterion was not met, along with the label given by [`describe`], if any.
     ///
     /// For a fallible assertion that can be called over and over without panicking, [`try_assert`]
     /// can be used instead.
     pub fn assert(&self) {
-        for criterion in &self.criteria {
-            criterion.assert(&self.entry_state);
+        let failures = self.check_all();
+        if failures.is_empty() {
+            return;
+        }
+
+        let prefix = match self.label.as_ref() {
+            Some(label) => format!("assertion '{}' failed", label),
+            None => "assertion failed".to_string(),
+        };
+        if failures.len() == 1 {
+            panic!("{}: span {} {}", prefix, self.matcher, failures[0]);
+        } else {
+            panic!("{}: span {}\n{}", prefix, self.matcher, failures);
+        }
+    }
+
+    /// Describes the assertion for panic messages which involve more than one assertion.
+    fn describe_for_panic(&self) -> String {
+        match self.label.as_ref() {
+            Some(label) => format!("'{}' (span {})", label, self.matcher.describe()),
+            None => format!("span {}", self.matcher.describe()),
         }
     }
 
+    /// Checks every criterion, collecting all of the criteria which have not been met.
+    ///
+    /// Unlike [`try_assert`], which stops at the first criterion which has not been met, this
+    /// evaluates every criterion, which makes it possible to see everything that's wrong at once.
+    /// If all criteria have been met, the returned failures will be empty.
+    pub fn check_all(&self) -> AssertionFailures {
+        let failures = self
+            .criteria
+            .iter()
+            .filter(|criterion| !criterion.try_assert(&self.entry_state))
+            .map(|criterion| AssertionFailure {
+                criterion: criterion.to_string(),
+                reason: criterion.describe_failure(&self.entry_state),
+            })
+            .collect();
+
+        AssertionFailures { failures }
+    }
+
     /// Attempts to assert that all criteria have been met.
     ///
     /// If any of the criteria have not yet been met, `false` will be returned.  Otherwise, `true`
@@ -112,7 +526,7 @@ impl Assertion {
     ///
     /// If assertions should end your test immediately, [`assert`] can be used instead.
     pub fn try_assert(&self) -> bool {
-        for criterion in &self.criteria {
+        for criterion in self.criteria.iter() {
             if !criterion.try_assert(&self.entry_state) {
                 return false;
             }
@@ -120,11 +534,290 @@ impl Assertion {
 
         true
     }
+
+    /// Blocks the current thread until all criteria have been met, or until `timeout` elapses.
+    ///
+    /// Returns `true` if all criteria were met before the timeout elapsed, and `false` otherwise.
+    ///
+    /// Rather than polling, the criteria are checked again each time a matching span is observed
+    /// doing something, which makes this suitable for waiting on spans produced by other threads
+    /// without needing an async runtime.  Criteria which can only be met by time passing, rather
+    /// than by matching spans doing something, such as [`total_duration_at_least`] for a span
+    /// which is still entered, are only checked again once the next change is observed.
+    pub fn block_until_satisfied(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+
+        // The generation is captured before checking the criteria, so that any change which
+        // happens in between causes the wait to return immediately rather than being missed.
+        let mut generation = self.entry_state.generation();
+        loop {
+            if self.try_assert() {
+                return true;
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+
+            generation = self.entry_state.wait_for_change(generation, deadline - now);
+        }
+    }
+
+    /// Deregisters the assertion, immediately removing it from its registry.
+    ///
+    /// This is equivalent to dropping the assertion, but makes the intent explicit.  As the
+    /// assertion is consumed, it cannot be used afterwards.
+    ///
+    /// Assertions with identical span matchers share the same underlying state, which is only
+    /// removed once all of them have been deregistered or dropped.  Until then, the remaining
+    /// assertions continue to be updated as normal.
+    pub fn deregister(self) {
+        drop(self);
+    }
+
+    /// Resets all state collected for matching spans.
+    ///
+    /// All lifecycle counts, events, durations, and recorded values are cleared, as if no matching
+    /// spans had been seen yet.  This allows the criteria to be checked against only the spans seen
+    /// after the reset, such as when a test has a setup phase followed by the phase under test.
+    ///
+    /// Assertions with identical span matchers share the same underlying state, and so resetting
+    /// one of them resets all of them.
+    pub fn reset(&self) {
+        self.entry_state.reset();
+    }
+
+    /// Starts a window over which lifecycle counts are captured.
+    ///
+    /// The counts for matching spans at this point are snapshotted, and [`window_counts`] will
+    /// then report how the counts have changed since.  Unlike [`reset`], this does not clear any
+    /// state, so the assertion criteria continue to be checked against the cumulative counts.
+    ///
+    /// Each assertion has a single window, so windows cannot be nested or overlap: starting a
+    /// window while one is already open, or after one has been stopped, discards it and starts a
+    /// new window from this point.
+    pub fn start_window(&self) {
+        let start = self.entry_state.snapshot();
+        *self
+            .window
+            .lock()
+            .expect("i literally don't know what a poisoned thread is") =
+            Some(Window { start, stop: None });
+    }
+
+    /// Stops the current window, fixing its counts at their current values.
+    ///
+    /// If no window has been started, or the window has already been stopped, this does nothing.
+    pub fn stop_window(&self) {
+        let mut window = self
+            .window
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        if let Some(window) = window.as_mut() {
+            if window.stop.is_none() {
+                window.stop = Some(self.entry_state.snapshot());
+            }
+        }
+    }
+
+    /// Gets the lifecycle counts for matching spans within the current window.
+    ///
+    /// If the window has been stopped, these are the counts between [`start_window`] and
+    /// [`stop_window`].  If the window is still open, these are the counts since [`start_window`].
+    /// If no window has been started, all counts are zero.
+    ///
+    /// Counts never go below zero, even if the assertion is [`reset`] while the window is open.
+    pub fn window_counts(&self) -> EntryCounts {
+        let window = self
+            .window
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        match window.as_ref() {
+            Some(window) => {
+                let end = window.stop.unwrap_or_else(|| self.entry_state.snapshot());
+                end.since(&window.start)
+            }
+            None => EntryCounts::default(),
+        }
+    }
+
+    /// Gets the current lifecycle counts for matching spans.
+    pub fn counts(&self) -> EntryCounts {
+        self.entry_state.snapshot()
+    }
+
+    /// Gets the instant at which a matching span was first entered.
+    ///
+    /// If no matching span has been entered yet, `None` is returned.
+    pub fn first_entered_at(&self) -> Option<Instant> {
+        self.entry_state.first_entered_at()
+    }
+
+    /// Gets the IDs of all matching spans.
+    ///
+    /// IDs are recorded when a matching span is created, and are returned in the order the spans
+    /// were created, as given by [`tracing::Id::into_u64`].  Spans created before the assertion
+    /// was finalized are not included.
+    ///
+    /// Span IDs are only unique among spans which are currently open, and subscribers such as
+    /// `tracing_subscriber::Registry` will reuse the ID of a closed span for new spans, so the same
+    /// ID may be present multiple times if multiple matching spans were given that ID.
+    ///
+    /// Only the IDs of the 1024 most recently created matching spans are kept, so that spans
+    /// created in a loop don't grow the list of IDs without bound.
+    pub fn matched_span_ids(&self) -> Vec<u64> {
+        self.entry_state.matched_ids()
+    }
+
+    /// Gets all values recorded for the given field on matching spans.
+    ///
+    /// Values are returned in the order they were recorded, which includes both the values a span
+    /// was created with and any values recorded on the span afterwards.  If the field was never
+    /// recorded on a matching span, an empty vector is returned.
+    ///
+    /// Only the 1024 most recently recorded values are kept for each field, so that spans created
+    /// in a loop don't grow the recorded values without bound.
+    pub fn recorded_values(&self, field: &str) -> Vec<FieldValueSnapshot> {
+        self.entry_state.recorded_values(field)
+    }
+
+    /// Gets the busy duration of matching spans.
+    ///
+    /// A span is busy from the time it is entered until the time it is exited, and the busy time of
+    /// every matching span, across every time it was entered, is summed together.  This is the same
+    /// duration checked by [`AssertionBuilder::total_duration_at_most`] and
+    /// [`AssertionBuilder::busy_duration_at_most`].  Spans which are currently entered do not
+    /// contribute their current interval until they exit.
+    ///
+    /// Unlike [`idle_duration`], this includes spans which are still open, so the two only add up
+    /// to the time between each span being created and closed once every matching span has closed.
+    ///
+    /// [`idle_duration`]: Assertion::idle_duration
+    pub fn busy_duration(&self) -> Duration {
+        self.entry_state.total_duration()
+    }
+
+    /// Gets the idle duration of matching spans.
+    ///
+    /// A span is idle whenever it is open but not entered, such as while the future it
+    /// instruments is waiting to be polled again, and so its idle time is the time between it being
+    /// created and closed, less its busy time.  The idle time of every matching span is summed
+    /// together.  As the total time of a span is only known once it closes, only spans which have
+    /// closed contribute to the idle duration, whereas [`busy_duration`] also includes spans which
+    /// are still open.
+    ///
+    /// [`busy_duration`]: Assertion::busy_duration
+    pub fn idle_duration(&self) -> Duration {
+        self.entry_state.idle_duration()
+    }
+
+    /// Gets the ordered lifecycle stages of the matching span with the given ID.
+    ///
+    /// Every stage of a matching span is recorded in order, from the time it is created until it
+    /// is closed, and is kept after the span has closed so that it can be inspected afterwards.
+    /// IDs can be found via [`matched_span_ids`].  As span IDs are reused once a span has closed,
+    /// this is the trace of the most recent matching span with the given ID.  Only the first 64
+    /// stages of each span are recorded.  If no matching span had the given ID, an empty vector is
+    /// returned.
+    pub fn lifecycle_trace(&self, span_id: u64) -> Vec<Stage> {
+        self.entry_state.lifecycle_trace(span_id)
+    }
+
+    /// Gets the lifecycle counts for matching spans, broken down by span name.
+    ///
+    /// When the span matcher can match spans with different names, such as when using
+    /// [`with_any_name`] or [`with_predicate`], this shows how the aggregate counts used by the
+    /// assertion criteria are split between the concrete span names.  Only names of spans which
+    /// have actually been seen are present.
+    pub fn breakdown_by_name(&self) -> HashMap<String, EntryCounts> {
+        self.entry_state.counts_by_name()
+    }
+}
+
+impl fmt::Debug for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("Assertion");
+        if let Some(label) = self.label.as_ref() {
+            debug.field("label", label);
+        }
+        debug
+            .field("matcher", &self.matcher)
+            .field("criteria", &self.criteria)
+            .field("created", &self.entry_state.num_created())
+            .field("entered", &self.entry_state.num_entered())
+            .field("exited", &self.entry_state.num_exited())
+            .field("closed", &self.entry_state.num_closed())
+            .finish()
+    }
 }
 
 impl Drop for Assertion {
     fn drop(&mut self) {
-        self.state.remove_entry(&self.matcher);
+        self.state.deregister_assertion(self.id);
+        self.entry_state.clear_fail_fast(self.id);
+        self.state.remove_entry(&self.matcher, &self.entry_state);
+    }
+}
+
+/// A criterion which has not been met, as returned by [`Assertion::check_all`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssertionFailure {
+    /// The description of the criterion.
+    pub criterion: String,
+
+    /// A description of how the criterion was not met.
+    pub reason: String,
+}
+
+impl fmt::Display for AssertionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+/// All criteria of an assertion which have not been met, as returned by [`Assertion::check_all`].
+///
+/// When displayed, each failure is rendered on its own line as a bulleted list.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AssertionFailures {
+    failures: Vec<AssertionFailure>,
+}
+
+impl AssertionFailures {
+    /// Consumes the failures, returning them as a vector.
+    pub fn into_vec(self) -> Vec<AssertionFailure> {
+        self.failures
+    }
+}
+
+impl Deref for AssertionFailures {
+    type Target = [AssertionFailure];
+
+    fn deref(&self) -> &Self::Target {
+        &self.failures
+    }
+}
+
+impl IntoIterator for AssertionFailures {
+    type Item = AssertionFailure;
+    type IntoIter = std::vec::IntoIter<AssertionFailure>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.failures.into_iter()
+    }
+}
+
+impl fmt::Display for AssertionFailures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, failure) in self.failures.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "- {}", failure)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -169,9 +862,109 @@ pub struct AssertionBuilder<S> {
     state: Arc<State>,
     matcher: Option<SpanMatcher>,
     criteria: Vec<AssertionCriterion>,
+    label: Option<String>,
+    fail_fast: bool,
     _builder_state: PhantomData<fn(S)>,
 }
 
+/// Cloning a builder allows a common span matcher to be configured once and then used as the base
+/// for several assertions.  Each clone has its own criteria, so adding criteria to one clone does
+/// not affect any other.
+impl<S> Clone for AssertionBuilder<S> {
+    fn clone(&self) -> Self {
+        AssertionBuilder {
+            state: Arc::clone(&self.state),
+            matcher: self.matcher.clone(),
+            criteria: self.criteria.clone(),
+            label: self.label.clone(),
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+}
+
+impl<S> AssertionBuilder<S> {
+    /// Sets a human-readable label for the assertion.
+    ///
+    /// The label is included in the panic message when [`Assertion::assert`] fails, which makes it
+    /// easier to tell which assertion failed when a test has many of them.
+    pub fn describe<L>(mut self, label: L) -> Self
+    where
+        L: Into<String>,
+    {
+        self.label = Some(label.into());
+        self
+    }
+
+    fn into_assertion(mut self) -> Assertion {
+        let matcher = self
+            .matcher
+            .take()
+            .expect("matcher must be present at this point");
+        let entry_state = self.state.create_entry(matcher.clone());
+
+        // Describing offending spans isn't free, so it's only done when there's a criterion which
+        // would actually use the description.
+        let has_negative_criteria = self.criteria.iter().any(|criterion| {
+            matches!(
+                criterion,
+                AssertionCriterion::WasNotCreated | AssertionCriterion::WasNotEntered
+            )
+        });
+        if has_negative_criteria {
+            entry_state.capture_offenders();
+        }
+
+        let criteria = Arc::new(self.criteria);
+        let id = self.state.register_assertion(Registration {
+            matcher: matcher.clone(),
+            label: self.label.clone(),
+            criteria: Arc::clone(&criteria),
+            entry_state: Arc::clone(&entry_state),
+            #[cfg(feature = "diagnostics")]
+            satisfied: criteria
+                .iter()
+                .all(|criterion| criterion.try_assert(&entry_state)),
+        });
+
+        if self.fail_fast {
+            let prefix = match self.label.as_ref() {
+                Some(label) => format!("assertion '{}' failed", label),
+                None => "assertion failed".to_string(),
+            };
+            for criterion in criteria.iter() {
+                match criterion {
+                    AssertionCriterion::WasNotCreated => entry_state.fail_fast_on_created(
+                        id,
+                        format!(
+                            "{}: span {} was created, expected never, offending span",
+                            prefix, matcher
+                        ),
+                    ),
+                    AssertionCriterion::WasNotEntered => entry_state.fail_fast_on_entered(
+                        id,
+                        format!(
+                            "{}: span {} was entered, expected never, offending span",
+                            prefix, matcher
+                        ),
+                    ),
+                    _ => {}
+                }
+            }
+        }
+
+        Assertion {
+            state: Arc::clone(&self.state),
+            entry_state,
+            matcher,
+            criteria,
+            label: self.label,
+            id,
+            window: Mutex::new(None),
+        }
+    }
+}
+
 impl AssertionBuilder<NoMatcher> {
     /// Sets the name of the span to match.
     ///
@@ -189,48 +982,67 @@ impl AssertionBuilder<NoMatcher> {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Sets the target of the span to match.
+    /// Sets the names of the span to match, any of which may match.
+    ///
+    /// The span matches if its name is any one of the given names, which allows a single assertion
+    /// to cover a logical operation that may be instrumented under different span names.  All
+    /// matching spans, regardless of which name they matched, contribute to the same criteria.
+    ///
+    /// This is independent of [`with_name`]: if both are set, the span name must be the name given
+    /// to [`with_name`] _and_ be one of the names given here.  Calling this method again replaces
+    /// the previously-given names.
     ///
     /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
     /// [`with_span_field`], are additive, which means a span must match all of them to match the
     /// assertion overall.
-    pub fn with_target<S>(mut self, target: S) -> AssertionBuilder<NoCriteria>
+    pub fn with_any_name<I, S>(mut self, names: I) -> AssertionBuilder<NoCriteria>
     where
+        I: IntoIterator<Item = S>,
         S: Into<String>,
     {
         let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
-        matcher.set_target(target.into());
+        matcher.set_any_names(names.into_iter().map(Into::into).collect());
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
-}
 
-impl AssertionBuilder<NoCriteria> {
-    /// Sets the name of the span to match.
+    /// Sets the ID of the span to match.
+    ///
+    /// This matches a single, specific span instance, which is useful when many spans share the
+    /// same name but only one of them is of interest.  As the ID of a span is only known once the
+    /// span has been created, the span will not be counted as having been created, so this is
+    /// typically used with an ID obtained from an existing span, such as via [`tracing::Span::id`],
+    /// to assert on what happens to that span afterwards.
+    ///
+    /// Span IDs may be reused once a span has closed, so a different span may be matched if it's
+    /// given the same ID after the original span closes.
     ///
     /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
     /// [`with_span_field`], are additive, which means a span must match all of them to match the
     /// assertion overall.
-    pub fn with_name<S>(mut self, name: S) -> AssertionBuilder<NoCriteria>
-    where
-        S: Into<String>,
-    {
+    pub fn with_span_id(mut self, id: Id) -> AssertionBuilder<NoCriteria> {
         let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
-        matcher.set_name(name.into());
+        matcher.set_id(id);
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
@@ -251,75 +1063,1007 @@ impl AssertionBuilder<NoCriteria> {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Sets the name of a parent span to match.
+    /// Sets the source location of the span to match.
     ///
-    /// The span must have at least one parent span within its entire lineage that matches the given
-    /// name.
+    /// The location is the file and line at which the span was created, as captured in its metadata
+    /// by the `tracing` macros, and is compared against the values of `file!()` and `line!()` at
+    /// that call site.  This allows picking out a single call site when the same span name is used
+    /// in several places.  Spans whose metadata does not include a location never match.
     ///
     /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
     /// [`with_span_field`], are additive, which means a span must match all of them to match the
     /// assertion overall.
-    pub fn with_parent_name<S>(mut self, name: S) -> AssertionBuilder<NoCriteria>
+    pub fn with_location<S>(mut self, file: S, line: u32) -> AssertionBuilder<NoCriteria>
     where
         S: Into<String>,
     {
         let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
-        matcher.set_parent_name(name.into());
+        matcher.set_location(file.into(), line);
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Adds a field which the span must contain to match.
+    /// Sets a regular expression which the target of the span must match.
     ///
-    /// The field is matched by name.
+    /// The regular expression can match anywhere within the target, so `^` and `$` should be used
+    /// to anchor it where needed, such as `^myapp::(db|cache)::`.  Unlike
+    /// [`with_target`], this is unaffected by [`ignore_case`], so use the `(?i)` flag instead to
+    /// match case-insensitively.
     ///
-    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
-    /// are additive, which means a span must match all of them to match the assertion overall.
-    pub fn with_span_field<S>(mut self, field: S) -> AssertionBuilder<NoCriteria>
-    where
-        S: Into<String>,
-    {
-        if let Some(matcher) = self.matcher.as_mut() {
-            matcher.add_field_exists(field.into());
-        }
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is not a valid regular expression.
+    #[cfg(feature = "regex")]
+    pub fn with_target_regex(mut self, pattern: &str) -> AssertionBuilder<NoCriteria> {
+        let regex = regex::Regex::new(pattern).expect("target regex must be valid");
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_target_regex(regex);
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was created at least once.
-    pub fn was_created(mut self) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::WasCreated);
+    /// Sets the name of a parent span to match.
+    ///
+    /// The span must have at least one parent span within its entire lineage that matches the given
+    /// name.  This can be called multiple times to require parent spans with each of the given
+    /// names, such as a span which must be nested somewhere under both a `transaction` span and a
+    /// `request` span.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_parent_name<S>(mut self, name: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_parent_name(name.into());
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was entered at least once.
-    pub fn was_entered(mut self) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::WasEntered);
-
-        AssertionBuilder {
+    /// Sets the name of a span which the span must follow from.
+    ///
+    /// Spans linked with `Span::follows_from`, rather than nested under one another, are not part
+    /// of each other's lineage, and so are not considered by [`with_parent_name`].  This instead
+    /// requires that the span follows directly from a span with the given name.  It can be called
+    /// multiple times to require that the span follows from spans with each of the given names.
+    ///
+    /// `tracing-subscriber`'s registry does not store follows-from links itself, so they are
+    /// captured by [`AssertionsLayer`][crate::AssertionsLayer] as they are made, which has a few
+    /// limitations:
+    ///
+    /// - Links are only added once a span already exists, so a span does not match when it is
+    ///   created, and will not count towards criteria such as [`was_created`], but will count
+    ///   towards criteria about anything which happens to it once it has been linked.
+    /// - Links to spans which have already closed cannot be captured, as their names are no longer
+    ///   available.
+    /// - Only direct links are considered, and not the spans that the followed span itself
+    ///   follows from or is nested under.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_follows_from_name<S>(mut self, name: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_follows_from_name(name.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets the target of a parent span to match.
+    ///
+    /// The span must have at least one parent span within its entire lineage that matches the given
+    /// target.  When combined with [`with_parent_name`], both must be satisfied, but not
+    /// necessarily by the same parent span.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_parent_target<S>(mut self, target: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_parent_target(target.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Requires that the span has no parent span.
+    ///
+    /// This matches only spans which are the root of their trace tree, and is mutually exclusive
+    /// with [`with_parent_name`] and [`with_parent_target`], as a span cannot both have no parent
+    /// and have a matching parent.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_no_parent(mut self) -> AssertionBuilder<NoCriteria> {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_no_parent();
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Requires that the span was created on the current thread.
+    ///
+    /// The current thread is the thread calling this method, and so this restricts the assertion to
+    /// spans created by the test itself, ignoring any spans created by background threads it may
+    /// spawn, even if they would otherwise match.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn on_current_thread(mut self) -> AssertionBuilder<NoCriteria> {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_thread(thread::current().id());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Compares span names and targets without regard to ASCII case.
+    ///
+    /// This applies to the names and targets given to [`with_name`], [`with_any_name`],
+    /// [`with_target`], [`with_parent_name`], and [`with_parent_target`], such that a span named
+    /// `Request` would match `with_name("request")`.  Field names are still compared exactly.
+    pub fn ignore_case(mut self) -> AssertionBuilder<NoCriteria> {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_ignore_case();
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a field which the span must contain to match.
+    ///
+    /// The field is matched by name.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
+    /// are additive, which means a span must match all of them to match the assertion overall.
+    pub fn with_span_field<S>(mut self, field: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_field_exists(field.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a field which must have been recorded on the span for it to match.
+    ///
+    /// The field is matched by name.  Unlike [`with_span_field`], which matches as long as the
+    /// field is declared, this only matches once a value has actually been recorded for the field,
+    /// either when the span was created or later via `Span::record`.  A field declared with
+    /// `field::Empty` which has not yet been recorded does not match.
+    ///
+    /// As a span which only has the field recorded after it was created did not match when it was
+    /// created, it will not count towards criteria such as [`was_created`], but will count towards
+    /// criteria about anything which happens to it once the field has been recorded.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
+    /// are additive, which means a span must match all of them to match the assertion overall.
+    pub fn with_recorded_field<S>(mut self, field: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_field_recorded(field.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a field whose most recently recorded value must have the given `Debug` rendering for
+    /// the span to match.
+    ///
+    /// This is primarily intended for fields recorded using `?value`, such as enums, where
+    /// `status = ?Status::Pending` can be matched with an expected value of `"Pending"`.  Values
+    /// recorded as primitives are compared using their own `Debug` rendering, so strings must
+    /// include their surrounding quotes, such as `"\"pending\""`.
+    ///
+    /// The value is checked each time the span is observed, so if a different value is later
+    /// recorded for the field, the span stops matching from that point on.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
+    /// are additive, which means a span must match all of them to match the assertion overall.
+    pub fn with_field_value_debug<S, V>(
+        mut self,
+        field: S,
+        expected: V,
+    ) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+        V: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_field_debug_value(field.into(), expected.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a numeric field whose most recently recorded value must be within the given range for
+    /// the span to match.
+    ///
+    /// The bounds can be any primitive integer or floating-point type.  Integer values are compared
+    /// with integer bounds exactly, whether either was signed or unsigned, while comparisons
+    /// involving a floating-point value or bound are made as floating-point numbers.  A field which
+    /// has not yet been recorded, or whose value was recorded as anything other than a number, such
+    /// as a string or with `?value`, does not match.
+    ///
+    /// The value is checked each time the span is observed, so the field does not have to be
+    /// recorded when the span is created.  However, a span whose value is only recorded, or only
+    /// falls within the range, after it was created did not match when it was created, so it will
+    /// not count towards criteria such as [`was_created`], but will count towards criteria about
+    /// anything which happens to it from then on.  Likewise, if a value outside the range is later
+    /// recorded, the span stops matching from that point on.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
+    /// are additive, which means a span must match all of them to match the assertion overall.
+    pub fn with_field_in_range<S, T>(
+        mut self,
+        field: S,
+        range: RangeInclusive<T>,
+    ) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+        T: FieldBound,
+    {
+        let (min, max) = range.into_inner();
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_field_in_range(field.into(), min.into_snapshot(), max.into_snapshot());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a field whose most recently recorded value must have been recorded with the given type
+    /// for the span to match.
+    ///
+    /// This only considers the type of the value, not the value itself, which is useful for
+    /// catching changes to instrumentation, such as a numeric field being recorded as a string.
+    /// A field which has been declared but not yet recorded does not match any type.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
+    /// are additive, which means a span must match all of them to match the assertion overall.
+    pub fn with_field_typed<S>(
+        mut self,
+        field: S,
+        field_type: FieldType,
+    ) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_field_typed(field.into(), field_type);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets the exact set of fields which the span must have to match.
+    ///
+    /// The fields are matched by name, and the span must declare all of the given fields and no
+    /// others.  This is stricter than [`with_span_field`], which only requires that the given field
+    /// is present.  Fields are considered present as long as they are declared on the span, even if
+    /// no value was ever recorded for them.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_exact_fields<I, S>(mut self, fields: I) -> AssertionBuilder<NoCriteria>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_exact_fields(fields.into_iter().map(Into::into).collect());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a custom predicate which the span must satisfy to match.
+    ///
+    /// The predicate is given a [`SpanInfo`], which provides access to the metadata of the span as
+    /// well as the most recent value recorded for each of its fields.  This allows expressing
+    /// criteria which the built-in span matchers cannot, such as a numeric field exceeding a given
+    /// threshold.  Multiple predicates may be added, and all of them must be satisfied.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_predicate<F>(mut self, predicate: F) -> AssertionBuilder<NoCriteria>
+    where
+        F: Fn(&SpanInfo<'_>) -> bool + Send + Sync + 'static,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_predicate(predicate);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+}
+
+impl AssertionBuilder<NoCriteria> {
+    /// Sets the name of the span to match.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_name<S>(mut self, name: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_name(name.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets the names of the span to match, any of which may match.
+    ///
+    /// The span matches if its name is any one of the given names, which allows a single assertion
+    /// to cover a logical operation that may be instrumented under different span names.  All
+    /// matching spans, regardless of which name they matched, contribute to the same criteria.
+    ///
+    /// This is independent of [`with_name`]: if both are set, the span name must be the name given
+    /// to [`with_name`] _and_ be one of the names given here.  Calling this method again replaces
+    /// the previously-given names.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_any_name<I, S>(mut self, names: I) -> AssertionBuilder<NoCriteria>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_any_names(names.into_iter().map(Into::into).collect());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets the ID of the span to match.
+    ///
+    /// This matches a single, specific span instance, which is useful when many spans share the
+    /// same name but only one of them is of interest.  As the ID of a span is only known once the
+    /// span has been created, the span will not be counted as having been created, so this is
+    /// typically used with an ID obtained from an existing span, such as via [`tracing::Span::id`],
+    /// to assert on what happens to that span afterwards.
+    ///
+    /// Span IDs may be reused once a span has closed, so a different span may be matched if it's
+    /// given the same ID after the original span closes.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_span_id(mut self, id: Id) -> AssertionBuilder<NoCriteria> {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_id(id);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets the target of the span to match.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_target<S>(mut self, target: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_target(target.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets the source location of the span to match.
+    ///
+    /// The location is the file and line at which the span was created, as captured in its metadata
+    /// by the `tracing` macros, and is compared against the values of `file!()` and `line!()` at
+    /// that call site.  This allows picking out a single call site when the same span name is used
+    /// in several places.  Spans whose metadata does not include a location never match.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_location<S>(mut self, file: S, line: u32) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_location(file.into(), line);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets a regular expression which the target of the span must match.
+    ///
+    /// The regular expression can match anywhere within the target, so `^` and `$` should be used
+    /// to anchor it where needed, such as `^myapp::(db|cache)::`.  Unlike
+    /// [`with_target`], this is unaffected by [`ignore_case`], so use the `(?i)` flag instead to
+    /// match case-insensitively.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is not a valid regular expression.
+    #[cfg(feature = "regex")]
+    pub fn with_target_regex(mut self, pattern: &str) -> AssertionBuilder<NoCriteria> {
+        let regex = regex::Regex::new(pattern).expect("target regex must be valid");
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_target_regex(regex);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets the name of a parent span to match.
+    ///
+    /// The span must have at least one parent span within its entire lineage that matches the given
+    /// name.  This can be called multiple times to require parent spans with each of the given
+    /// names, such as a span which must be nested somewhere under both a `transaction` span and a
+    /// `request` span.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_parent_name<S>(mut self, name: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_parent_name(name.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets the name of a span which the span must follow from.
+    ///
+    /// Spans linked with `Span::follows_from`, rather than nested under one another, are not part
+    /// of each other's lineage, and so are not considered by [`with_parent_name`].  This instead
+    /// requires that the span follows directly from a span with the given name.  It can be called
+    /// multiple times to require that the span follows from spans with each of the given names.
+    ///
+    /// `tracing-subscriber`'s registry does not store follows-from links itself, so they are
+    /// captured by [`AssertionsLayer`][crate::AssertionsLayer] as they are made, which has a few
+    /// limitations:
+    ///
+    /// - Links are only added once a span already exists, so a span does not match when it is
+    ///   created, and will not count towards criteria such as [`was_created`], but will count
+    ///   towards criteria about anything which happens to it once it has been linked.
+    /// - Links to spans which have already closed cannot be captured, as their names are no longer
+    ///   available.
+    /// - Only direct links are considered, and not the spans that the followed span itself
+    ///   follows from or is nested under.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_follows_from_name<S>(mut self, name: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_follows_from_name(name.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets the target of a parent span to match.
+    ///
+    /// The span must have at least one parent span within its entire lineage that matches the given
+    /// target.  When combined with [`with_parent_name`], both must be satisfied, but not
+    /// necessarily by the same parent span.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_parent_target<S>(mut self, target: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_parent_target(target.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Requires that the span has no parent span.
+    ///
+    /// This matches only spans which are the root of their trace tree, and is mutually exclusive
+    /// with [`with_parent_name`] and [`with_parent_target`], as a span cannot both have no parent
+    /// and have a matching parent.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_no_parent(mut self) -> AssertionBuilder<NoCriteria> {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_no_parent();
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Requires that the span was created on the current thread.
+    ///
+    /// The current thread is the thread calling this method, and so this restricts the assertion to
+    /// spans created by the test itself, ignoring any spans created by background threads it may
+    /// spawn, even if they would otherwise match.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn on_current_thread(mut self) -> AssertionBuilder<NoCriteria> {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_thread(thread::current().id());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Compares span names and targets without regard to ASCII case.
+    ///
+    /// This applies to the names and targets given to [`with_name`], [`with_any_name`],
+    /// [`with_target`], [`with_parent_name`], and [`with_parent_target`], such that a span named
+    /// `Request` would match `with_name("request")`.  Field names are still compared exactly.
+    pub fn ignore_case(mut self) -> AssertionBuilder<NoCriteria> {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.set_ignore_case();
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a field which the span must contain to match.
+    ///
+    /// The field is matched by name.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
+    /// are additive, which means a span must match all of them to match the assertion overall.
+    pub fn with_span_field<S>(mut self, field: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_field_exists(field.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a field which must have been recorded on the span for it to match.
+    ///
+    /// The field is matched by name.  Unlike [`with_span_field`], which matches as long as the
+    /// field is declared, this only matches once a value has actually been recorded for the field,
+    /// either when the span was created or later via `Span::record`.  A field declared with
+    /// `field::Empty` which has not yet been recorded does not match.
+    ///
+    /// As a span which only has the field recorded after it was created did not match when it was
+    /// created, it will not count towards criteria such as [`was_created`], but will count towards
+    /// criteria about anything which happens to it once the field has been recorded.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
+    /// are additive, which means a span must match all of them to match the assertion overall.
+    pub fn with_recorded_field<S>(mut self, field: S) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_field_recorded(field.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a field whose most recently recorded value must have the given `Debug` rendering for
+    /// the span to match.
+    ///
+    /// This is primarily intended for fields recorded using `?value`, such as enums, where
+    /// `status = ?Status::Pending` can be matched with an expected value of `"Pending"`.  Values
+    /// recorded as primitives are compared using their own `Debug` rendering, so strings must
+    /// include their surrounding quotes, such as `"\"pending\""`.
+    ///
+    /// The value is checked each time the span is observed, so if a different value is later
+    /// recorded for the field, the span stops matching from that point on.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
+    /// are additive, which means a span must match all of them to match the assertion overall.
+    pub fn with_field_value_debug<S, V>(
+        mut self,
+        field: S,
+        expected: V,
+    ) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+        V: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_field_debug_value(field.into(), expected.into());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a numeric field whose most recently recorded value must be within the given range for
+    /// the span to match.
+    ///
+    /// The bounds can be any primitive integer or floating-point type.  Integer values are compared
+    /// with integer bounds exactly, whether either was signed or unsigned, while comparisons
+    /// involving a floating-point value or bound are made as floating-point numbers.  A field which
+    /// has not yet been recorded, or whose value was recorded as anything other than a number, such
+    /// as a string or with `?value`, does not match.
+    ///
+    /// The value is checked each time the span is observed, so the field does not have to be
+    /// recorded when the span is created.  However, a span whose value is only recorded, or only
+    /// falls within the range, after it was created did not match when it was created, so it will
+    /// not count towards criteria such as [`was_created`], but will count towards criteria about
+    /// anything which happens to it from then on.  Likewise, if a value outside the range is later
+    /// recorded, the span stops matching from that point on.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
+    /// are additive, which means a span must match all of them to match the assertion overall.
+    pub fn with_field_in_range<S, T>(
+        mut self,
+        field: S,
+        range: RangeInclusive<T>,
+    ) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+        T: FieldBound,
+    {
+        let (min, max) = range.into_inner();
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_field_in_range(field.into(), min.into_snapshot(), max.into_snapshot());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a field whose most recently recorded value must have been recorded with the given type
+    /// for the span to match.
+    ///
+    /// This only considers the type of the value, not the value itself, which is useful for
+    /// catching changes to instrumentation, such as a numeric field being recorded as a string.
+    /// A field which has been declared but not yet recorded does not match any type.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], and [`with_span_field`],
+    /// are additive, which means a span must match all of them to match the assertion overall.
+    pub fn with_field_typed<S>(
+        mut self,
+        field: S,
+        field_type: FieldType,
+    ) -> AssertionBuilder<NoCriteria>
+    where
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_field_typed(field.into(), field_type);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Sets the exact set of fields which the span must have to match.
+    ///
+    /// The fields are matched by name, and the span must declare all of the given fields and no
+    /// others.  This is stricter than [`with_span_field`], which only requires that the given field
+    /// is present.  Fields are considered present as long as they are declared on the span, even if
+    /// no value was ever recorded for them.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_exact_fields<I, S>(mut self, fields: I) -> AssertionBuilder<NoCriteria>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_exact_fields(fields.into_iter().map(Into::into).collect());
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Adds a custom predicate which the span must satisfy to match.
+    ///
+    /// The predicate is given a [`SpanInfo`], which provides access to the metadata of the span as
+    /// well as the most recent value recorded for each of its fields.  This allows expressing
+    /// criteria which the built-in span matchers cannot, such as a numeric field exceeding a given
+    /// threshold.  Multiple predicates may be added, and all of them must be satisfied.
+    ///
+    /// All span matchers, which includes [`with_name`], [`with_target`], [`with_parent_name`], and
+    /// [`with_span_field`], are additive, which means a span must match all of them to match the
+    /// assertion overall.
+    pub fn with_predicate<F>(mut self, predicate: F) -> AssertionBuilder<NoCriteria>
+    where
+        F: Fn(&SpanInfo<'_>) -> bool + Send + Sync + 'static,
+    {
+        let matcher = self.matcher.get_or_insert_with(SpanMatcher::default);
+        matcher.add_predicate(predicate);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Creates the finalized `Assertion` without any criteria.
+    ///
+    /// This is useful when only the state collected for matching spans is of interest, such as
+    /// via [`Assertion::counts`], rather than asserting anything about it.  As there are no
+    /// criteria, [`Assertion::assert`] never panics and [`Assertion::try_assert`] always returns
+    /// `true`.
+    pub fn finalize_observer(self) -> Assertion {
+        self.into_assertion()
+    }
+
+    /// Asserts that a matching span was created at least once.
+    pub fn was_created(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::WasCreated);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was entered at least once.
+    pub fn was_entered(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::WasEntered);
+
+        AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
@@ -332,6 +2076,8 @@ impl AssertionBuilder<NoCriteria> {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
@@ -344,150 +2090,600 @@ impl AssertionBuilder<NoCriteria> {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was not created.
+    ///
+    /// If this criterion is not met, the failure describes the first matching span that was created.
+    pub fn was_not_created(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::WasNotCreated);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was not entered.
+    ///
+    /// If this criterion is not met, the failure describes the first matching span that was entered.
+    pub fn was_not_entered(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::WasNotEntered);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was not exited.
+    pub fn was_not_exited(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::WasNotExited);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was not closed.
+    pub fn was_not_closed(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::WasNotClosed);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was created exactly `n` times.
+    pub fn was_created_exactly(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::CreatedExactly(n));
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was entered exactly `n` times.
+    pub fn was_entered_exactly(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::EnteredExactly(n));
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was exited exactly `n` times.
+    pub fn was_exited_exactly(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::ExitedExactly(n));
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was closed exactly `n` times.
+    pub fn was_closed_exactly(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::ClosedExactly(n));
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was created at least `n` times.
+    pub fn was_created_at_least(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::CreatedAtLeast(n));
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was entered at least `n` times.
+    pub fn was_entered_at_least(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::EnteredAtLeast(n));
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was exited at least `n` times.
+    pub fn was_exited_at_least(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::ExitedAtLeast(n));
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was closed at least `n` times.
+    pub fn was_closed_at_least(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::ClosedAtLeast(n));
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that exactly `n` distinct instances of a matching span were entered.
+    ///
+    /// Unlike [`was_entered_exactly`], which counts every time a matching span is entered, this
+    /// counts each span instance only once, no matter how many times it was entered.
+    pub fn distinct_entered_exactly(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria
+            .push(AssertionCriterion::DistinctEnteredExactly(n));
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that every instance of a matching span which was created was also entered at least
+    /// once.
+    ///
+    /// This catches spans which are created and then dropped without ever being entered, such as
+    /// when a span is constructed but `.entered()` or `.in_scope(...)` is never called on it.  As
+    /// spans which are still open may yet be entered, this criterion should generally be checked
+    /// once matching spans have closed.
+    pub fn every_instance_entered(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::EveryInstanceEntered);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that no instance of a matching span was entered more than once.
+    ///
+    /// Unlike [`was_entered_exactly`], which counts every time any matching span is entered, this
+    /// considers each span instance separately, and so is unaffected by how many instances there
+    /// are.  This catches spans being entered again after being exited, such as a span guard
+    /// being reacquired, as well as spans being entered again while still entered.
+    pub fn every_instance_entered_at_most_once(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria
+            .push(AssertionCriterion::EveryInstanceEnteredAtMostOnce);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that no instance of a matching span was entered again while it was already entered.
+    ///
+    /// `tracing` allows a span to be entered again before it has exited, such as by entering it
+    /// within its own scope, and every such enter counts towards [`was_entered_exactly`].  This
+    /// instead tracks how deeply each span instance is entered, and fails if any instance was ever
+    /// entered more than once at the same time.  Unlike [`every_instance_entered_at_most_once`],
+    /// a span which is exited and then entered again is not considered to have been reentered.
+    pub fn was_never_reentered(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::NeverReentered);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span went through exactly the given sequence of lifecycle stages.
+    ///
+    /// Aggregate counts can't tell whether a span was, for example, entered twice in a row rather
+    /// than entered and exited, so this instead compares the ordered stages of each matching span
+    /// instance, such as `[Created, Entered, Exited, Entered, Exited, Closed]`.  At least one
+    /// matching span must have gone through exactly the given stages, no more and no less, so spans
+    /// which are still open only match a sequence which doesn't end with [`Stage::Closed`].  Only
+    /// the first 64 stages of each span are recorded, so spans which go through more stages than
+    /// that never match, not even a sequence made of their first 64 stages.
+    pub fn had_lifecycle_sequence<I>(mut self, expected: I) -> AssertionBuilder<Constrained>
+    where
+        I: IntoIterator<Item = Stage>,
+    {
+        self.criteria.push(AssertionCriterion::LifecycleSequence(
+            expected.into_iter().collect(),
+        ));
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span was the current span at least once.
+    ///
+    /// The current span is the innermost span which has been entered, but not yet exited, on the
+    /// current thread.  A span becomes current when it is entered, or when a span entered within it
+    /// is exited.  However, entering a span which has already been entered further up the stack
+    /// does not make it current again, as `tracing` only considers the first time a span was
+    /// entered, and so a span may be entered without ever being current, unlike with
+    /// [`was_entered`].
+    pub fn was_current(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::WasCurrent);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that matching spans were never exited more times than they were entered.
+    ///
+    /// This is checked for each span instance as it is exited, rather than by comparing the final
+    /// counts, and so catches transient imbalances, such as a span guard being dropped twice, even
+    /// if the counts are balanced by the time the assertion is checked.  Exiting a span which was
+    /// entered before the assertion was finalized, or before it was reset, is not an imbalance.
+    pub fn was_never_over_exited(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::NeverOverExited);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
+            _builder_state: PhantomData,
+        }
+    }
+
+    /// Asserts that a matching span is currently open, having been entered more times than it has
+    /// been exited.
+    ///
+    /// This is a point-in-time check: it is evaluated against the counts as they are when the
+    /// assertion is checked, and so its result depends entirely on when [`assert`] or
+    /// [`try_assert`] is called relative to span guards being dropped.  It is useful for proving
+    /// that a span guard is still held at a specific point in a test.
+    pub fn is_currently_open(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::CurrentlyOpen);
+
+        AssertionBuilder {
+            state: self.state,
+            matcher: self.matcher,
+            criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was not created.
-    pub fn was_not_created(mut self) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::WasNotCreated);
+    /// Asserts that no matching span is currently open, having been exited as many times as it has
+    /// been entered.
+    ///
+    /// This is the inverse of [`is_currently_open`], and is likewise a point-in-time check whose
+    /// result depends on when [`assert`] or [`try_assert`] is called relative to span guards being
+    /// dropped.
+    pub fn is_currently_closed(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::CurrentlyClosed);
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was not entered.
-    pub fn was_not_entered(mut self) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::WasNotEntered);
+    /// Asserts that the number of times matching spans reached one lifecycle stage relates to the
+    /// number of times they reached another stage in the given way.
+    ///
+    /// The comparison is `lhs.cmp(rhs) == ordering`, so, for example, passing `Stage::Entered`,
+    /// `Ordering::Greater`, and `Stage::Exited` asserts that matching spans were entered more times
+    /// than they were exited.
+    pub fn assert_relation(
+        mut self,
+        lhs: Stage,
+        ordering: Ordering,
+        rhs: Stage,
+    ) -> AssertionBuilder<Constrained> {
+        self.criteria
+            .push(AssertionCriterion::Relation(lhs, ordering, rhs));
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was not exited.
-    pub fn was_not_exited(mut self) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::WasNotExited);
+    /// Asserts that matching spans were closed as many times as they were created.
+    ///
+    /// This differs from balanced entering and exiting, as a span can be entered and exited any
+    /// number of times while still never being closed, such as when a span handle is leaked.
+    pub fn created_equals_closed(self) -> AssertionBuilder<Constrained> {
+        self.assert_relation(Stage::Created, Ordering::Equal, Stage::Closed)
+    }
+
+    /// Asserts that matching spans were exited as many times as they were entered.
+    pub fn entered_equals_exited(self) -> AssertionBuilder<Constrained> {
+        self.assert_relation(Stage::Entered, Ordering::Equal, Stage::Exited)
+    }
+
+    /// Asserts that no child spans were created within matching spans.
+    ///
+    /// A matching span is marked as having children as soon as a child span is created within it,
+    /// so this criterion fails immediately once that happens.  However, whether or not a span is a
+    /// leaf is only final once it closes, as a child span could still be created within a span
+    /// which is currently open, so this criterion should generally be checked once matching spans
+    /// have closed.
+    pub fn had_no_children(mut self) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::HadNoChildren);
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was not closed.
-    pub fn was_not_closed(mut self) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::WasNotClosed);
+    /// Asserts that matching spans were entered on at least `n` distinct threads.
+    ///
+    /// Threads are distinguished by their OS thread, so when using a single-threaded async runtime,
+    /// matching spans will only ever be entered on one thread.
+    pub fn entered_on_at_least_n_threads(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria
+            .push(AssertionCriterion::EnteredOnAtLeastThreads(n));
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was created exactly `n` times.
-    pub fn was_created_exactly(mut self, n: usize) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::CreatedExactly(n));
+    /// Asserts that matching spans were nested under at most `n` parent spans.
+    ///
+    /// The depth of a span is the number of spans in its lineage, such that a span with no parent
+    /// has a depth of zero, and its direct children have a depth of one.  The depth is determined
+    /// when the span is created, and the maximum depth of all matching spans is compared.
+    pub fn max_depth_at_most(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::MaxDepthAtMost(n));
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was entered exactly `n` times.
-    pub fn was_entered_exactly(mut self, n: usize) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::EnteredExactly(n));
+    /// Asserts that every matching span was nested under exactly `n` parent spans.
+    ///
+    /// The depth of a span is the number of spans in its lineage, such that a span with no parent
+    /// has a depth of zero, and its direct children have a depth of one.  The depth is determined
+    /// when the span is created.  Unlike [`max_depth_at_most`], spans nested less deeply than
+    /// expected also fail, which catches both missing and accidental extra nesting.  If no
+    /// matching span has been created, this criterion passes.
+    pub fn had_depth_exactly(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria.push(AssertionCriterion::DepthExactly(n));
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was exited exactly `n` times.
-    pub fn was_exited_exactly(mut self, n: usize) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::ExitedExactly(n));
+    /// Asserts that at least `n` events were emitted within a matching span.
+    ///
+    /// An event is considered to be within a matching span if the span, or any of its parent spans,
+    /// is the event's parent.  Each event is counted at most once, regardless of how many spans in
+    /// its scope match.  Events emitted outside of any span are never counted.
+    pub fn emitted_event_at_least(mut self, n: usize) -> AssertionBuilder<Constrained> {
+        self.criteria
+            .push(AssertionCriterion::EmittedEventAtLeast(n));
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was closed exactly `n` times.
-    pub fn was_closed_exactly(mut self, n: usize) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::ClosedExactly(n));
+    /// Asserts that at least one event with the given level was emitted within a matching span.
+    ///
+    /// An event is considered to be within a matching span if the span, or any of its parent spans,
+    /// is the event's parent.  Events emitted outside of any span are never counted.
+    pub fn emitted_event_with_level(mut self, level: Level) -> AssertionBuilder<Constrained> {
+        self.criteria
+            .push(AssertionCriterion::EmittedEventWithLevel(level));
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was created at least `n` times.
-    pub fn was_created_at_least(mut self, n: usize) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::CreatedAtLeast(n));
+    /// Asserts that the given field was recorded at least `n` times on matching spans.
+    ///
+    /// Only values recorded after a span was created, such as via `Span::record`, are counted, and
+    /// each recording counts separately, so recording the field on the same span in a loop counts
+    /// every time.  Values a span was created with are not counted.
+    pub fn field_recorded_at_least<S>(mut self, field: S, n: usize) -> AssertionBuilder<Constrained>
+    where
+        S: Into<String>,
+    {
+        self.criteria
+            .push(AssertionCriterion::FieldRecordedAtLeast(field.into(), n));
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was entered at least `n` times.
-    pub fn was_entered_at_least(mut self, n: usize) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::EnteredAtLeast(n));
+    /// Asserts that matching spans were active for at most `duration` in total.
+    ///
+    /// A span is active from the time it is entered until the time it is exited, and the active
+    /// time of every matching span, across every time it was entered, is summed together.  Spans
+    /// which are entered again before exiting are only considered active until their outermost
+    /// exit.  Spans which are currently entered do not contribute their current interval to the
+    /// total until they exit.
+    pub fn total_duration_at_most(mut self, duration: Duration) -> AssertionBuilder<Constrained> {
+        self.criteria
+            .push(AssertionCriterion::TotalDurationAtMost(duration));
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was exited at least `n` times.
-    pub fn was_exited_at_least(mut self, n: usize) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::ExitedAtLeast(n));
+    /// Asserts that matching spans were active for at least `duration` in total.
+    ///
+    /// A span is active from the time it is entered until the time it is exited, and the active
+    /// time of every matching span, across every time it was entered, is summed together.  Spans
+    /// which are entered again before exiting are only considered active until their outermost
+    /// exit.  Spans which are currently entered do not contribute their current interval to the
+    /// total until they exit.
+    pub fn total_duration_at_least(mut self, duration: Duration) -> AssertionBuilder<Constrained> {
+        self.criteria
+            .push(AssertionCriterion::TotalDurationAtLeast(duration));
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
 
-    /// Asserts that a matching span was closed at least `n` times.
-    pub fn was_closed_at_least(mut self, n: usize) -> AssertionBuilder<Constrained> {
-        self.criteria.push(AssertionCriterion::ClosedAtLeast(n));
+    /// Asserts that matching spans were busy for at most `duration` in total.
+    ///
+    /// A span is busy from the time it is entered until the time it is exited, as opposed to being
+    /// idle while it is open but not entered, and the busy time of every matching span is summed
+    /// together.  This is the same as [`total_duration_at_most`], but makes it explicit that time
+    /// spent idle, such as an instrumented future waiting to be polled, is not counted.  See
+    /// [`Assertion::busy_duration`] and [`Assertion::idle_duration`].
+    pub fn busy_duration_at_most(mut self, duration: Duration) -> AssertionBuilder<Constrained> {
+        self.criteria
+            .push(AssertionCriterion::BusyDurationAtMost(duration));
 
         AssertionBuilder {
             state: self.state,
             matcher: self.matcher,
             criteria: self.criteria,
+            label: self.label,
+            fail_fast: self.fail_fast,
             _builder_state: PhantomData,
         }
     }
@@ -519,12 +2715,16 @@ impl AssertionBuilder<Constrained> {
     }
 
     /// Asserts that a matching span was not created.
+    ///
+    /// If this criterion is not met, the failure describes the first matching span that was created.
     pub fn was_not_created(mut self) -> Self {
         self.criteria.push(AssertionCriterion::WasNotCreated);
         self
     }
 
     /// Asserts that a matching span was not entered.
+    ///
+    /// If this criterion is not met, the failure describes the first matching span that was entered.
     pub fn was_not_entered(mut self) -> Self {
         self.criteria.push(AssertionCriterion::WasNotEntered);
         self
@@ -590,24 +2790,488 @@ impl AssertionBuilder<Constrained> {
         self
     }
 
+    /// Asserts that exactly `n` distinct instances of a matching span were entered.
+    ///
+    /// Unlike [`was_entered_exactly`], which counts every time a matching span is entered, this
+    /// counts each span instance only once, no matter how many times it was entered.
+    pub fn distinct_entered_exactly(mut self, n: usize) -> Self {
+        self.criteria
+            .push(AssertionCriterion::DistinctEnteredExactly(n));
+        self
+    }
+
+    /// Asserts that every instance of a matching span which was created was also entered at least
+    /// once.
+    ///
+    /// This catches spans which are created and then dropped without ever being entered, such as
+    /// when a span is constructed but `.entered()` or `.in_scope(...)` is never called on it.  As
+    /// spans which are still open may yet be entered, this criterion should generally be checked
+    /// once matching spans have closed.
+    pub fn every_instance_entered(mut self) -> Self {
+        self.criteria.push(AssertionCriterion::EveryInstanceEntered);
+        self
+    }
+
+    /// Asserts that no instance of a matching span was entered more than once.
+    ///
+    /// Unlike [`was_entered_exactly`], which counts every time any matching span is entered, this
+    /// considers each span instance separately, and so is unaffected by how many instances there
+    /// are.  This catches spans being entered again after being exited, such as a span guard
+    /// being reacquired, as well as spans being entered again while still entered.
+    pub fn every_instance_entered_at_most_once(mut self) -> Self {
+        self.criteria
+            .push(AssertionCriterion::EveryInstanceEnteredAtMostOnce);
+        self
+    }
+
+    /// Asserts that no instance of a matching span was entered again while it was already entered.
+    ///
+    /// `tracing` allows a span to be entered again before it has exited, such as by entering it
+    /// within its own scope, and every such enter counts towards [`was_entered_exactly`].  This
+    /// instead tracks how deeply each span instance is entered, and fails if any instance was ever
+    /// entered more than once at the same time.  Unlike [`every_instance_entered_at_most_once`],
+    /// a span which is exited and then entered again is not considered to have been reentered.
+    pub fn was_never_reentered(mut self) -> Self {
+        self.criteria.push(AssertionCriterion::NeverReentered);
+        self
+    }
+
+    /// Asserts that a matching span went through exactly the given sequence of lifecycle stages.
+    ///
+    /// Aggregate counts can't tell whether a span was, for example, entered twice in a row rather
+    /// than entered and exited, so this instead compares the ordered stages of each matching span
+    /// instance, such as `[Created, Entered, Exited, Entered, Exited, Closed]`.  At least one
+    /// matching span must have gone through exactly the given stages, no more and no less, so spans
+    /// which are still open only match a sequence which doesn't end with [`Stage::Closed`].  Only
+    /// the first 64 stages of each span are recorded, so spans which go through more stages than
+    /// that never match, not even a sequence made of their first 64 stages.
+    pub fn had_lifecycle_sequence<I>(mut self, expected: I) -> Self
+    where
+        I: IntoIterator<Item = Stage>,
+    {
+        self.criteria.push(AssertionCriterion::LifecycleSequence(
+            expected.into_iter().collect(),
+        ));
+        self
+    }
+
+    /// Asserts that a matching span was the current span at least once.
+    ///
+    /// The current span is the innermost span which has been entered, but not yet exited, on the
+    /// current thread.  A span becomes current when it is entered, or when a span entered within it
+    /// is exited.  However, entering a span which has already been entered further up the stack
+    /// does not make it current again, as `tracing` only considers the first time a span was
+    /// entered, and so a span may be entered without ever being current, unlike with
+    /// [`was_entered`].
+    pub fn was_current(mut self) -> Self {
+        self.criteria.push(AssertionCriterion::WasCurrent);
+        self
+    }
+
+    /// Asserts that matching spans were never exited more times than they were entered.
+    ///
+    /// This is checked for each span instance as it is exited, rather than by comparing the final
+    /// counts, and so catches transient imbalances, such as a span guard being dropped twice, even
+    /// if the counts are balanced by the time the assertion is checked.  Exiting a span which was
+    /// entered before the assertion was finalized, or before it was reset, is not an imbalance.
+    pub fn was_never_over_exited(mut self) -> Self {
+        self.criteria.push(AssertionCriterion::NeverOverExited);
+        self
+    }
+
+    /// Asserts that a matching span is currently open, having been entered more times than it has
+    /// been exited.
+    ///
+    /// This is a point-in-time check: it is evaluated against the counts as they are when the
+    /// assertion is checked, and so its result depends entirely on when [`assert`] or
+    /// [`try_assert`] is called relative to span guards being dropped.  It is useful for proving
+    /// that a span guard is still held at a specific point in a test.
+    pub fn is_currently_open(mut self) -> Self {
+        self.criteria.push(AssertionCriterion::CurrentlyOpen);
+        self
+    }
+
+    /// Asserts that no matching span is currently open, having been exited as many times as it has
+    /// been entered.
+    ///
+    /// This is the inverse of [`is_currently_open`], and is likewise a point-in-time check whose
+    /// result depends on when [`assert`] or [`try_assert`] is called relative to span guards being
+    /// dropped.
+    pub fn is_currently_closed(mut self) -> Self {
+        self.criteria.push(AssertionCriterion::CurrentlyClosed);
+        self
+    }
+
+    /// Asserts that the number of times matching spans reached one lifecycle stage relates to the
+    /// number of times they reached another stage in the given way.
+    ///
+    /// The comparison is `lhs.cmp(rhs) == ordering`, so, for example, passing `Stage::Entered`,
+    /// `Ordering::Greater`, and `Stage::Exited` asserts that matching spans were entered more times
+    /// than they were exited.
+    pub fn assert_relation(mut self, lhs: Stage, ordering: Ordering, rhs: Stage) -> Self {
+        self.criteria
+            .push(AssertionCriterion::Relation(lhs, ordering, rhs));
+        self
+    }
+
+    /// Asserts that matching spans were closed as many times as they were created.
+    ///
+    /// This differs from balanced entering and exiting, as a span can be entered and exited any
+    /// number of times while still never being closed, such as when a span handle is leaked.
+    pub fn created_equals_closed(self) -> Self {
+        self.assert_relation(Stage::Created, Ordering::Equal, Stage::Closed)
+    }
+
+    /// Asserts that matching spans were exited as many times as they were entered.
+    pub fn entered_equals_exited(self) -> Self {
+        self.assert_relation(Stage::Entered, Ordering::Equal, Stage::Exited)
+    }
+
+    /// Asserts that no child spans were created within matching spans.
+    ///
+    /// A matching span is marked as having children as soon as a child span is created within it,
+    /// so this criterion fails immediately once that happens.  However, whether or not a span is a
+    /// leaf is only final once it closes, as a child span could still be created within a span
+    /// which is currently open, so this criterion should generally be checked once matching spans
+    /// have closed.
+    pub fn had_no_children(mut self) -> Self {
+        self.criteria.push(AssertionCriterion::HadNoChildren);
+        self
+    }
+
+    /// Asserts that matching spans were entered on at least `n` distinct threads.
+    ///
+    /// Threads are distinguished by their OS thread, so when using a single-threaded async runtime,
+    /// matching spans will only ever be entered on one thread.
+    pub fn entered_on_at_least_n_threads(mut self, n: usize) -> Self {
+        self.criteria
+            .push(AssertionCriterion::EnteredOnAtLeastThreads(n));
+        self
+    }
+
+    /// Asserts that matching spans were nested under at most `n` parent spans.
+    ///
+    /// The depth of a span is the number of spans in its lineage, such that a span with no parent
+    /// has a depth of zero, and its direct children have a depth of one.  The depth is determined
+    /// when the span is created, and the maximum depth of all matching spans is compared.
+    pub fn max_depth_at_most(mut self, n: usize) -> Self {
+        self.criteria.push(AssertionCriterion::MaxDepthAtMost(n));
+        self
+    }
+
+    /// Asserts that every matching span was nested under exactly `n` parent spans.
+    ///
+    /// The depth of a span is the number of spans in its lineage, such that a span with no parent
+    /// has a depth of zero, and its direct children have a depth of one.  The depth is determined
+    /// when the span is created.  Unlike [`max_depth_at_most`], spans nested less deeply than
+    /// expected also fail, which catches both missing and accidental extra nesting.  If no
+    /// matching span has been created, this criterion passes.
+    pub fn had_depth_exactly(mut self, n: usize) -> Self {
+        self.criteria.push(AssertionCriterion::DepthExactly(n));
+        self
+    }
+
+    /// Asserts that at least `n` events were emitted within a matching span.
+    ///
+    /// An event is considered to be within a matching span if the span, or any of its parent spans,
+    /// is the event's parent.  Each event is counted at most once, regardless of how many spans in
+    /// its scope match.  Events emitted outside of any span are never counted.
+    pub fn emitted_event_at_least(mut self, n: usize) -> Self {
+        self.criteria
+            .push(AssertionCriterion::EmittedEventAtLeast(n));
+        self
+    }
+
+    /// Asserts that at least one event with the given level was emitted within a matching span.
+    ///
+    /// An event is considered to be within a matching span if the span, or any of its parent spans,
+    /// is the event's parent.  Events emitted outside of any span are never counted.
+    pub fn emitted_event_with_level(mut self, level: Level) -> Self {
+        self.criteria
+            .push(AssertionCriterion::EmittedEventWithLevel(level));
+        self
+    }
+
+    /// Asserts that the given field was recorded at least `n` times on matching spans.
+    ///
+    /// Only values recorded after a span was created, such as via `Span::record`, are counted, and
+    /// each recording counts separately, so recording the field on the same span in a loop counts
+    /// every time.  Values a span was created with are not counted.
+    pub fn field_recorded_at_least<S>(mut self, field: S, n: usize) -> Self
+    where
+        S: Into<String>,
+    {
+        self.criteria
+            .push(AssertionCriterion::FieldRecordedAtLeast(field.into(), n));
+        self
+    }
+
+    /// Asserts that matching spans were active for at most `duration` in total.
+    ///
+    /// A span is active from the time it is entered until the time it is exited, and the active
+    /// time of every matching span, across every time it was entered, is summed together.  Spans
+    /// which are entered again before exiting are only considered active until their outermost
+    /// exit.  Spans which are currently entered do not contribute their current interval to the
+    /// total until they exit.
+    pub fn total_duration_at_most(mut self, duration: Duration) -> Self {
+        self.criteria
+            .push(AssertionCriterion::TotalDurationAtMost(duration));
+        self
+    }
+
+    /// Asserts that matching spans were active for at least `duration` in total.
+    ///
+    /// A span is active from the time it is entered until the time it is exited, and the active
+    /// time of every matching span, across every time it was entered, is summed together.  Spans
+    /// which are entered again before exiting are only considered active until their outermost
+    /// exit.  Spans which are currently entered do not contribute their current interval to the
+    /// total until they exit.
+    pub fn total_duration_at_least(mut self, duration: Duration) -> Self {
+        self.criteria
+            .push(AssertionCriterion::TotalDurationAtLeast(duration));
+        self
+    }
+
+    /// Asserts that matching spans were busy for at most `duration` in total.
+    ///
+    /// A span is busy from the time it is entered until the time it is exited, as opposed to being
+    /// idle while it is open but not entered, and the busy time of every matching span is summed
+    /// together.  This is the same as [`total_duration_at_most`], but makes it explicit that time
+    /// spent idle, such as an instrumented future waiting to be polled, is not counted.  See
+    /// [`Assertion::busy_duration`] and [`Assertion::idle_duration`].
+    pub fn busy_duration_at_most(mut self, duration: Duration) -> Self {
+        self.criteria
+            .push(AssertionCriterion::BusyDurationAtMost(duration));
+        self
+    }
+
+    /// Makes negative criteria fail as soon as they are violated, by panicking immediately.
+    ///
+    /// Normally, criteria are only checked when [`Assertion::assert`] or a similar method is
+    /// called.  With this enabled, [`was_not_created`] and [`was_not_entered`] instead cause a
+    /// panic as soon as a matching span is created or entered, respectively, so that the backtrace
+    /// points at the code which created or entered the span.  Other criteria are unaffected, and
+    /// are still only checked when asked to.
+    ///
+    /// The panic happens inside of the layer's callback, in the middle of `tracing` handling the
+    /// span, which may be surprising: the panic may occur on a thread other than the test's, and
+    /// the subscriber may be left in an inconsistent state, such as the span never being closed.
+    /// As such, this is best reserved for tracking down where a forbidden span comes from.  This
+    /// applies to every assertion with the same span matcher for as long as this assertion exists.
+    pub fn fail_fast(mut self) -> Self {
+        self.fail_fast = true;
+        self
+    }
+
     /// Creates the finalized `Assertion`.
     ///
     /// Once finalized, the assertion is live and its state will be updated going forward.
-    pub fn finalize(mut self) -> Assertion {
-        let matcher = self
-            .matcher
-            .take()
-            .expect("matcher must be present at this point");
-        let entry_state = self.state.create_entry(matcher.clone());
-        Assertion {
-            state: Arc::clone(&self.state),
-            entry_state,
-            matcher,
-            criteria: self.criteria,
+    pub fn finalize(self) -> Assertion {
+        self.into_assertion()
+    }
+
+    /// Creates a finalized `Assertion` from the current configuration, without consuming the
+    /// builder.
+    ///
+    /// The builder can continue to be used afterwards, such as to add more criteria and create
+    /// further assertions.  Each assertion created this way is independent: it has the criteria
+    /// the builder had at the time it was created, and no others, and is registered and dropped
+    /// separately from any other assertion.
+    pub fn finalize_clone(&self) -> Assertion {
+        self.clone().into_assertion()
+    }
+
+    /// Creates the finalized `Assertion`, counting any matching spans which are already open.
+    ///
+    /// Normally, only spans created after the assertion is finalized are counted as having been
+    /// created, although spans which are already open are still counted when they are entered,
+    /// exited, or closed.  This makes it possible to finalize an assertion while spans are already
+    /// in flight on other threads without missing the creation of those spans.
+    ///
+    /// Open spans are found using the current default subscriber, which must be based on
+    /// `tracing_subscriber::Registry` and have an [`AssertionsLayer`] from the same registry as
+    /// this builder installed.  If the current default subscriber is not based on `Registry`, no
+    /// spans are found, and this behaves exactly like [`finalize`].  Open spans are matched as they
+    /// are at the time of finalizing, and so they only count towards criteria about spans being
+    /// created: there is no way to tell whether they have already been entered, for example.
+    ///
+    /// Assertions with identical span matchers share the same underlying state, so any open spans
+    /// found are counted for all of them.
+    ///
+    /// [`AssertionsLayer`]: crate::AssertionsLayer
+    #[cfg(feature = "backscan")]
+    pub fn finalize_with_backscan(self) -> Assertion {
+        use tracing_subscriber::registry::LookupSpan;
+
+        let assertion = self.into_assertion();
+
+        tracing::dispatcher::get_default(|dispatch| {
+            let registry = match dispatch.downcast_ref::<tracing_subscriber::Registry>() {
+                Some(registry) => registry,
+                None => return,
+            };
+
+            for (id, metadata) in assertion.state.open_spans() {
+                // The span may have closed in the meantime, and its ID may even have been reused,
+                // or it may belong to another subscriber with a layer from the same registry.
+                let span = match registry.span(&id) {
+                    Some(span) if std::ptr::eq(span.metadata(), metadata) => span,
+                    _ => continue,
+                };
+
+                // Spans created since the assertion was finalized will have already been tracked
+                // by the layer itself.
+                if assertion.matcher.matches(&span)
+                    && !assertion.entry_state.has_tracked_created(&id)
+                {
+                    assertion.entry_state.track_backscanned(&id, span.name());
+                }
+            }
+        });
+
+        assertion
+    }
+}
+
+/// The lifecycle counts of the spans matching an assertion.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EntryCounts {
+    /// The number of times a matching span was created.
+    pub created: usize,
+
+    /// The number of times a matching span was entered.
+    pub entered: usize,
+
+    /// The number of times a matching span was exited.
+    pub exited: usize,
+
+    /// The number of times a matching span was closed.
+    pub closed: usize,
+}
+
+impl EntryCounts {
+    fn since(&self, start: &EntryCounts) -> Self {
+        Self {
+            created: self.created.saturating_sub(start.created),
+            entered: self.entered.saturating_sub(start.entered),
+            exited: self.exited.saturating_sub(start.exited),
+            closed: self.closed.saturating_sub(start.closed),
         }
     }
 }
 
+/// A point-in-time view of the lifecycle counts for every assertion in a registry.
+///
+/// Assertions are identified by a key, such that snapshots taken from different registries -- or
+/// from the same registry at different times -- can be compared with [`diff`].  The key is the
+/// label of the first assertion registered for a span matcher, if it has one, and a description of
+/// the span matcher otherwise.  Assertions with identical span matchers share the same counts, and
+/// so appear only once.
+///
+/// Span matchers which only differ in ways their description can't capture, such as by predicate,
+/// would otherwise collide, so any key which is already taken has ` #2`, ` #3`, and so on appended,
+/// in the order the span matchers were registered.  Descriptions of span matchers which use span
+/// IDs or thread IDs differ from run to run, so labelling those assertions is recommended when
+/// comparing snapshots across runs.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RegistrySnapshot {
+    counts: BTreeMap<String, EntryCounts>,
+}
+
+impl RegistrySnapshot {
+    /// Gets the counts for every span matcher, keyed as described on [`RegistrySnapshot`].
+    pub fn counts(&self) -> &BTreeMap<String, EntryCounts> {
+        &self.counts
+    }
+
+    /// Computes the change in counts from this snapshot to `other`.
+    ///
+    /// A delta is returned for every span matcher whose counts differ between the two snapshots,
+    /// ordered by key.  Matchers present in only one of the snapshots
+    /// are treated as having counts of zero in the other.
+    pub fn diff(&self, other: &RegistrySnapshot) -> Vec<CountDelta> {
+        let mut matchers = self.counts.keys().collect::<Vec<_>>();
+        matchers.extend(other.counts.keys());
+        matchers.sort();
+        matchers.dedup();
+
+        matchers
+            .into_iter()
+            .filter_map(|matcher| {
+                let before = self.counts.get(matcher).copied().unwrap_or_default();
+                let after = other.counts.get(matcher).copied().unwrap_or_default();
+                if before == after {
+                    return None;
+                }
+
+                Some(CountDelta {
+                    matcher: matcher.clone(),
+                    created: after.created as i64 - before.created as i64,
+                    entered: after.entered as i64 - before.entered as i64,
+                    exited: after.exited as i64 - before.exited as i64,
+                    closed: after.closed as i64 - before.closed as i64,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The change in lifecycle counts for a single span matcher between two [`RegistrySnapshot`]s.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CountDelta {
+    /// The key of the span matcher, as described on [`RegistrySnapshot`].
+    pub matcher: String,
+
+    /// The change in the number of times a matching span was created.
+    pub created: i64,
+
+    /// The change in the number of times a matching span was entered.
+    pub entered: i64,
+
+    /// The change in the number of times a matching span was exited.
+    pub exited: i64,
+
+    /// The change in the number of times a matching span was closed.
+    pub closed: i64,
+}
+
+/// The outcome of a single assertion, as reported by [`AssertionRegistry::report`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AssertionReport {
+    /// The label of the assertion, if one was given with [`AssertionBuilder::describe`].
+    pub label: Option<String>,
+
+    /// The description of the span matcher.
+    pub matcher: String,
+
+    /// Whether or not all criteria have been met.
+    pub passed: bool,
+
+    /// The outcome of each criterion, in the order they were added.
+    pub criteria: Vec<CriterionReport>,
+
+    /// The lifecycle counts of the matching spans.
+    pub counts: EntryCounts,
+}
+
+/// The outcome of a single criterion within an [`AssertionReport`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CriterionReport {
+    /// The description of the criterion.
+    pub criterion: String,
+
+    /// Whether or not the criterion has been met.
+    pub passed: bool,
+
+    /// A description of how the criterion was not met, if it was not met.
+    pub failure: Option<String>,
+}
+
 /// Creates and stores all constructed [`Assertion`]s.
 #[derive(Clone, Default)]
 pub struct AssertionRegistry {
@@ -619,13 +3283,238 @@ impl AssertionRegistry {
         &self.state
     }
 
+    /// Creates a new [`AssertionRegistry`] with space for at least `capacity` distinct span
+    /// matchers.
+    ///
+    /// This avoids repeatedly growing the registry's internal storage when creating a large number
+    /// of assertions up front.  Assertions with identical span matchers share storage, so only
+    /// distinct span matchers need to be accounted for.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(State::with_capacity(capacity)),
+        }
+    }
+
+    /// Creates a new [`AssertionRegistry`] and installs it as the default subscriber for the current
+    /// thread.
+    ///
+    /// The subscriber consists of a `tracing_subscriber::Registry` with an [`AssertionsLayer`]
+    /// tied to the returned registry.  The subscriber remains the default until the returned
+    /// guard is dropped, so the guard must be held for as long as spans should be captured.
+    ///
+    /// [`AssertionsLayer`]: crate::AssertionsLayer
+    #[cfg(feature = "install")]
+    pub fn install() -> (AssertionRegistry, tracing::subscriber::DefaultGuard) {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let registry = AssertionRegistry::default();
+        let subscriber =
+            tracing_subscriber::Registry::default().with(crate::AssertionsLayer::new(&registry));
+        let guard = tracing::subscriber::set_default(subscriber);
+        (registry, guard)
+    }
+
     /// Creates an [`AssertionBuilder`] for constructing a new [`Assertion`].
     pub fn build(&self) -> AssertionBuilder<NoMatcher> {
         AssertionBuilder {
             state: Arc::clone(&self.state),
             matcher: None,
             criteria: Vec::new(),
+            label: None,
+            fail_fast: false,
             _builder_state: PhantomData,
         }
     }
+
+    /// Removes all assertions from the registry.
+    ///
+    /// The state of every assertion created by this registry is removed at once, which is useful
+    /// for ensuring that subsequent assertions start from a clean state when the registry is
+    /// long-lived.  Existing assertions remain usable, but they will no longer be updated as
+    /// matching spans are created, entered, and so on.
+    pub fn clear(&self) {
+        self.state.clear();
+    }
+
+    /// Takes a snapshot of the current lifecycle counts for every assertion in the registry.
+    pub fn snapshot(&self) -> RegistrySnapshot {
+        let entries = self.state.entries();
+        let labels = self.state.with_assertions(|assertions| {
+            entries
+                .iter()
+                .map(|(_, state)| {
+                    assertions
+                        .values()
+                        .filter(|registration| Arc::ptr_eq(&registration.entry_state, state))
+                        .find_map(|registration| registration.label.clone())
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let mut counts = BTreeMap::new();
+        for ((matcher, state), label) in entries.into_iter().zip(labels) {
+            let base = label.unwrap_or_else(|| matcher.describe());
+            let mut key = base.clone();
+            let mut n = 1;
+            while counts.contains_key(&key) {
+                n += 1;
+                key = format!("{} #{}", base, n);
+            }
+            counts.insert(key, state.snapshot());
+        }
+
+        RegistrySnapshot { counts }
+    }
+
+    /// Gets the current lifecycle counts for a span matcher, without creating an assertion.
+    ///
+    /// The span matcher is built by the given closure, in the same way as when building an
+    /// assertion, such as `registry.quick_check(|b| b.with_target("myapp::db"))`.
+    ///
+    /// Spans are only tracked for span matchers which have been registered, by finalizing an
+    /// assertion, and only from that point onwards, so this can only report counts when an
+    /// assertion with an identical span matcher already exists.  If there is no such assertion,
+    /// `None` is returned, rather than counts of zero, as there is no way to tell whether any
+    /// matching spans were created.  To check for spans ad hoc, finalize an assertion with
+    /// [`AssertionBuilder::finalize_observer`] before the code under test runs, and then either
+    /// keep it alive or query it here.
+    ///
+    /// Predicates added with `with_predicate` are compared by identity rather than by what they do,
+    /// as closures can't be compared, so a span matcher built here with a predicate is never
+    /// identical to a registered one, and `None` is always returned for it.
+    pub fn quick_check<F>(&self, build: F) -> Option<EntryCounts>
+    where
+        F: FnOnce(AssertionBuilder<NoMatcher>) -> AssertionBuilder<NoCriteria>,
+    {
+        let matcher = build(self.build())
+            .matcher
+            .expect("matcher must be present at this point");
+        self.state
+            .get_entry_state(&matcher)
+            .map(|state| state.snapshot())
+    }
+
+    /// Gets the span matchers of every assertion in the registry which have not matched any spans.
+    ///
+    /// A span matcher which has seen no activity at all -- no matching span has been created,
+    /// entered, exited, or closed -- usually means that the matcher is wrong, such as a typo in a
+    /// span name or target.  Criteria like [`was_not_created`] pass regardless in that case, so
+    /// checking that this is empty at the end of a test is a useful sanity check.
+    ///
+    /// Span matchers are identified by their description, and are sorted.
+    pub fn unmatched(&self) -> Vec<String> {
+        let mut unmatched = self
+            .state
+            .entries()
+            .into_iter()
+            .filter(|(_, state)| state.snapshot() == EntryCounts::default())
+            .map(|(matcher, _)| matcher.describe())
+            .collect::<Vec<_>>();
+        unmatched.sort();
+        unmatched
+    }
+
+    /// Asserts that two assertions matched the same span.
+    ///
+    /// This is useful for confirming that a single span has the properties checked by two
+    /// different span matchers, such as one matching by name and another by field, rather than
+    /// two separate spans each having one of them.  Spans are compared by their IDs, as given by
+    /// [`Assertion::matched_span_ids`], so only spans which matched when they were created are
+    /// considered.  If either assertion matched multiple spans, this passes as long as at least one
+    /// span was matched by both.
+    ///
+    /// Span IDs are reused once a span has closed, so two distinct spans which were given the same
+    /// ID, one after the other, are indistinguishable from a single span.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no span was matched by both assertions.
+    pub fn assert_same_instance(&self, a: &Assertion, b: &Assertion) {
+        let a_ids = a.matched_span_ids();
+        let b_ids = b.matched_span_ids();
+        if a_ids.iter().any(|id| b_ids.contains(id)) {
+            return;
+        }
+
+        panic!(
+            "assertion failed: {} and {} never matched the same span, matched {} and {} span(s) respectively",
+            a.describe_for_panic(),
+            b.describe_for_panic(),
+            a_ids.len(),
+            b_ids.len()
+        );
+    }
+
+    /// Reports the outcome of every assertion in the registry.
+    ///
+    /// Assertions are reported in the order they were created, and each criterion is evaluated in
+    /// the same way as [`Assertion::try_assert`], so generating a report never panics, even when
+    /// criteria have not been met.  With the `serde` feature enabled, reports can be serialized,
+    /// such as to attach a machine-readable summary of a test run as a CI artifact.
+    pub fn report(&self) -> Vec<AssertionReport> {
+        self.state.with_assertions(|assertions| {
+            assertions
+                .values()
+                .map(|registration| {
+                    let criteria = registration
+                        .criteria
+                        .iter()
+                        .map(|criterion| {
+                            let passed = criterion.try_assert(&registration.entry_state);
+                            CriterionReport {
+                                criterion: criterion.to_string(),
+                                passed,
+                                failure: if passed {
+                                    None
+                                } else {
+                                    Some(criterion.describe_failure(&registration.entry_state))
+                                },
+                            }
+                        })
+                        .collect::<Vec<_>>();
+
+                    AssertionReport {
+                        label: registration.label.clone(),
+                        matcher: registration.matcher.describe(),
+                        passed: criteria.iter().all(|criterion| criterion.passed),
+                        criteria,
+                        counts: registration.entry_state.snapshot(),
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// Asserts that a span matching `first` was entered before any span matching `second`.
+    ///
+    /// Only the first time a matching span was entered is considered for either assertion, so
+    /// spans matching `first` may be entered again after spans matching `second` without affecting
+    /// the outcome.
+    ///
+    /// Both assertions must have had a matching span entered at least once: if either has not, the
+    /// ordering cannot be established, and this will panic just as if `first` had been entered
+    /// after `second`.
+    ///
+    /// The order is determined by a sequence number taken when a matching span is first entered,
+    /// rather than by [`Assertion::first_entered_at`], as instants can be identical when spans are
+    /// entered in quick succession.  Two assertions with identical span matchers share the same
+    /// state, and so can never have been entered strictly one before the other.
+    pub fn assert_ordering(&self, first: &Assertion, second: &Assertion) {
+        let first_entered = first.entry_state.first_entered_sequence();
+        let second_entered = second.entry_state.first_entered_sequence();
+        let observed = match (first_entered, second_entered) {
+            (Some(first_entered), Some(second_entered)) if first_entered < second_entered => return,
+            (Some(_), Some(_)) => format!("{} was entered first", second.describe_for_panic()),
+            (None, Some(_)) => format!("{} was never entered", first.describe_for_panic()),
+            (Some(_), None) => format!("{} was never entered", second.describe_for_panic()),
+            (None, None) => "neither was ever entered".to_string(),
+        };
+
+        panic!(
+            "assertion failed: {} was expected to be entered before {}, but {}",
+            first.describe_for_panic(),
+            second.describe_for_panic(),
+            observed
+        );
+    }
 }
@@ -0,0 +1,85 @@
+//! Diagnostic events emitted when assertions become satisfied or violated.
+use std::cell::{Cell, RefCell};
+
+use crate::EntryCounts;
+
+/// The target of every diagnostic event.
+const TARGET: &str = "tracing_fluent_assertions::diagnostics";
+
+thread_local! {
+    static EMITTING: Cell<bool> = const { Cell::new(false) };
+    static PENDING: RefCell<Vec<Transition>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Whether diagnostic events are currently being emitted on this thread.
+///
+/// Diagnostic events are dispatched to the same subscriber as everything else, which may include
+/// the very layer that emitted them, so the layer uses this to ignore them.
+pub(crate) fn is_emitting() -> bool {
+    EMITTING.with(Cell::get)
+}
+
+/// An assertion whose criteria went from being satisfied to violated, or vice versa.
+pub(crate) struct Transition {
+    pub matcher: String,
+    pub label: Option<String>,
+    pub satisfied: bool,
+    pub counts: EntryCounts,
+}
+
+/// Resets the emitting flag when dropped, so that it's reset even if emitting an event panics.
+struct EmittingGuard;
+
+impl Drop for EmittingGuard {
+    fn drop(&mut self) {
+        EMITTING.with(|emitting| emitting.set(false));
+    }
+}
+
+/// Queues diagnostic events for the given transitions, to be emitted by the next call to [`flush`].
+pub(crate) fn queue(transitions: Vec<Transition>) {
+    if transitions.is_empty() {
+        return;
+    }
+
+    PENDING.with(|pending| pending.borrow_mut().extend(transitions));
+}
+
+/// Emits a diagnostic event for every queued transition.
+pub(crate) fn flush() {
+    if is_emitting() {
+        return;
+    }
+
+    let transitions = PENDING.with(|pending| pending.replace(Vec::new()));
+    if transitions.is_empty() {
+        return;
+    }
+
+    EMITTING.with(|emitting| emitting.set(true));
+    let _guard = EmittingGuard;
+
+    for transition in transitions {
+        let message = if transition.satisfied {
+            "assertion satisfied"
+        } else {
+            "assertion violated"
+        };
+
+        // Events are given an explicit lack of a parent so that, even if the layer didn't ignore
+        // them, they could never be attributed to a matching span.
+        tracing::trace!(
+            target: TARGET,
+            parent: None,
+            matcher = %transition.matcher,
+            label = transition.label.as_deref(),
+            satisfied = transition.satisfied,
+            created = transition.counts.created,
+            entered = transition.counts.entered,
+            exited = transition.counts.exited,
+            closed = transition.counts.closed,
+            "{}",
+            message,
+        );
+    }
+}
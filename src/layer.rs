@@ -1,17 +1,94 @@
-use std::{any::TypeId, marker::PhantomData, sync::Arc};
+use std::{
+    any::TypeId,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
 
-use tracing::{span::Attributes, Id, Subscriber};
-use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+use tracing::{
+    span::{Attributes, Record},
+    Event, Id, Subscriber,
+};
+use tracing_subscriber::{
+    layer::Context,
+    registry::{LookupSpan, SpanRef},
+    Layer,
+};
 
-use crate::{state::State, AssertionRegistry};
+#[cfg(feature = "diagnostics")]
+use crate::diagnostics;
+use crate::{
+    matcher::{CreatedOn, FollowsFrom},
+    state::State,
+    value::{FieldValueSnapshot, RecordedValues, ValueRecorder},
+    AssertionRegistry,
+};
 
 /// A [`tracing_subscriber::Layer`] that tracks the lifecycle changes of certain spans based on span
 /// matchers which define which spans to track.
+///
+/// ## Filtering
+///
+/// This layer is interested in every span, but it can only observe spans which are enabled for the
+/// subscriber as a whole.  A global filter -- a filter added to the subscriber as a layer in its own
+/// right, such as `.with(EnvFilter::from_default_env())` -- disables spans for every layer in the
+/// subscriber, and this layer cannot override that, regardless of where the filter is placed
+/// relative to it.
+///
+/// If spans should be filtered for other layers, such as a formatting layer, but still tracked by
+/// this layer, use per-layer filtering on those layers instead, via `Layer::with_filter`.
+/// Per-layer filters only affect the layer they're attached to, so this layer will continue to
+/// observe all spans.
+///
+/// Conversely, if this layer should only track the spans that a filtered layer actually records,
+/// such as a layer with a sampling filter, attach the same filter to this layer with
+/// `Layer::with_filter`.  Spans disabled by a per-layer filter still reach every other layer, and
+/// `tracing-subscriber` does not expose which per-layer filters disabled a given span, so this
+/// layer has no way to tell on its own whether a span it sees was recorded by any other layer.
+///
+/// ## Sharing a registry
+///
+/// Multiple layers can be created from the same [`AssertionRegistry`], and their spans will all
+/// count towards the same assertions, whether the layers are installed in different subscribers --
+/// such as when a test swaps subscribers partway through -- or all in the same subscriber.  In the
+/// latter case, each span is only tracked by the first of those layers to see it, so that it isn't
+/// counted multiple times.
+///
+/// ## Diagnostics
+///
+/// With the `diagnostics` feature enabled, this layer emits a `TRACE` event with the target
+/// `tracing_fluent_assertions::diagnostics` whenever an assertion goes from having its criteria
+/// satisfied to violated, or vice versa, which shows the order in which expectations were met.
+/// Each event includes the span matcher, the label of the assertion, if any, and the current
+/// lifecycle counts.
+///
+/// Diagnostic events go to the same subscriber as everything else, and so to this layer as well.
+/// To avoid them feeding back into the assertions, they are emitted without a parent span, so they
+/// can't be attributed to any matching span, and this layer ignores any event emitted while it is
+/// itself emitting diagnostic events.  When a scoped default subscriber is in use, `tracing` drops
+/// events emitted while a span is being created or an event is being dispatched, so diagnostic
+/// events caused by those are delayed until matching spans are next entered, exited, recorded, or
+/// closed.
 pub struct AssertionsLayer<S> {
     state: Arc<State>,
+    id: usize,
     _subscriber: PhantomData<fn(S)>,
 }
 
+static NEXT_LAYER_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The layers which are tracking a span, stored in the span's extensions.
+///
+/// Each registry the span is being tracked for maps to the single layer tracking the span on its
+/// behalf.
+#[derive(Default)]
+struct TrackedBy {
+    layers: Vec<(usize, usize)>,
+}
+
 impl<S> AssertionsLayer<S>
 where
     S: Subscriber,
@@ -20,41 +97,282 @@ where
     pub fn new(controller: &AssertionRegistry) -> Self {
         Self {
             state: Arc::clone(controller.state()),
+            id: NEXT_LAYER_ID.fetch_add(1, Ordering::Relaxed),
             _subscriber: PhantomData,
         }
     }
 }
 
+impl<S> AssertionsLayer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn state_id(&self) -> usize {
+        Arc::as_ptr(&self.state) as usize
+    }
+
+    /// Attempts to claim the given span for tracking by this layer.
+    ///
+    /// Returns `true` if this layer should track the span, or `false` if another layer tied to the
+    /// same registry has already claimed it.
+    fn claim(&self, span: &SpanRef<'_, S>) -> bool {
+        let mut extensions = span.extensions_mut();
+        if extensions.get_mut::<TrackedBy>().is_none() {
+            extensions.insert(TrackedBy::default());
+        }
+
+        let tracked_by = extensions
+            .get_mut::<TrackedBy>()
+            .expect("tracked by must exist");
+        match tracked_by
+            .layers
+            .iter()
+            .find(|(state_id, _)| *state_id == self.state_id())
+        {
+            Some((_, layer_id)) => *layer_id == self.id,
+            None => {
+                tracked_by.layers.push((self.state_id(), self.id));
+                true
+            }
+        }
+    }
+
+    /// Emits diagnostic events for any assertions which have become satisfied or violated.
+    #[cfg(feature = "diagnostics")]
+    fn emit_diagnostics(&self) {
+        self.state.check_transitions();
+
+        // `tracing` drops any events emitted while a scoped default subscriber is already busy
+        // creating a span or dispatching an event, so they're held back until the next time this
+        // layer is called when they'll actually make it to the subscriber.
+        if tracing::dispatcher::get_default(|dispatch| dispatch.is::<Self>()) {
+            diagnostics::flush();
+        }
+    }
+
+    /// Whether or not this layer is tracking the given span.
+    fn is_tracking(&self, span: &SpanRef<'_, S>) -> bool {
+        let extensions = span.extensions();
+        match extensions.get::<TrackedBy>() {
+            Some(tracked_by) => tracked_by
+                .layers
+                .iter()
+                .all(|(state_id, layer_id)| *state_id != self.state_id() || *layer_id == self.id),
+            None => true,
+        }
+    }
+}
+
 impl<S> Layer<S> for AssertionsLayer<S>
 where
     S: Subscriber + for<'a> LookupSpan<'a>,
 {
-    fn on_new_span(&self, _attributes: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span must already exist!");
+        if !self.claim(&span) {
+            return;
+        }
+
+        #[cfg(feature = "backscan")]
+        self.state.track_open_span(id, span.metadata());
+
+        // Anything matchers may depend on has to be stored on the span before matching.  Layers
+        // from other registries in the same subscriber will have stored the same thread already,
+        // and extensions can only be inserted once.
+        let mut extensions = span.extensions_mut();
+        if extensions.get_mut::<CreatedOn>().is_none() {
+            extensions.insert(CreatedOn(thread::current().id()));
+        }
+        drop(extensions);
+
+        let mut recorder = ValueRecorder::default();
+        attributes.record(&mut recorder);
+        let values = recorder.into_values();
+        store_recorded_values(&span, &values);
+
+        let entries = self.state.get_entries(&span);
+        if !entries.is_empty() {
+            // Walking the lineage of every span would be wasteful, so we only do it for spans which
+            // actually match.
+            let depth = span.scope().skip(1).count();
+
+            for entry in &entries {
+                entry.track_created(id, span.name());
+                entry.track_created_offender(|| describe_span(&span));
+                entry.track_values(&values);
+                entry.track_depth(depth);
+            }
+        }
+
+        if let Some(parent) = span.parent() {
+            let parent_id = parent.id();
+            for entry in self.state.get_entries(&parent) {
+                entry.track_child(&parent_id);
+            }
+        }
+
+        // Failing fast panics, so it has to wait until the span has been fully tracked.
+        for entry in &entries {
+            entry.check_fail_fast_created(|| describe_span(&span));
+        }
+
+        #[cfg(feature = "diagnostics")]
+        self.emit_diagnostics();
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).expect("span must already exist!");
+        if !self.is_tracking(&span) {
+            return;
+        }
+
+        let mut recorder = ValueRecorder::default();
+        values.record(&mut recorder);
+        let values = recorder.into_values();
+        store_recorded_values(&span, &values);
+
+        for entry in self.state.get_entries(&span) {
+            entry.track_values(&values);
+            entry.track_recorded(&values);
+        }
+
+        #[cfg(feature = "diagnostics")]
+        self.emit_diagnostics();
+    }
+
+    fn on_follows_from(&self, id: &Id, follows: &Id, ctx: Context<'_, S>) {
         let span = ctx.span(id).expect("span must already exist!");
-        if let Some(entry) = self.state.get_entry(span) {
-            entry.track_created();
+        if !self.is_tracking(&span) {
+            return;
+        }
+
+        // The registry doesn't keep track of follows-from links itself, so we store the name of
+        // the span being followed for matchers to check against.  If it has already closed, there's
+        // no way to find out what its name was.
+        let follows = match ctx.span(follows) {
+            Some(follows) => follows,
+            None => return,
+        };
+
+        let mut extensions = span.extensions_mut();
+        match extensions.get_mut::<FollowsFrom>() {
+            Some(FollowsFrom(names)) => names.push(follows.name()),
+            None => {
+                extensions.insert(FollowsFrom(vec![follows.name()]));
+            }
         }
     }
 
     fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
         let span = ctx.span(id).expect("span must already exist!");
-        if let Some(entry) = self.state.get_entry(span) {
-            entry.track_entered();
+        if !self.is_tracking(&span) {
+            return;
         }
+
+        // Entering a span which is already entered further up the stack doesn't make it the
+        // current span again, so the span isn't necessarily current just because it was entered.
+        let is_current = ctx.current_span().id() == Some(id);
+
+        let entries = self.state.get_entries(&span);
+        for entry in &entries {
+            entry.track_entered(id, span.name());
+            entry.track_entered_offender(|| describe_span(&span));
+            if is_current {
+                entry.track_current();
+            }
+        }
+
+        // Failing fast panics, so it has to wait until the span has been fully tracked.
+        for entry in &entries {
+            entry.check_fail_fast_entered(|| describe_span(&span));
+        }
+
+        #[cfg(feature = "diagnostics")]
+        self.emit_diagnostics();
     }
 
     fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
         let span = ctx.span(id).expect("span must already exist!");
-        if let Some(entry) = self.state.get_entry(span) {
-            entry.track_exited();
+        if !self.is_tracking(&span) {
+            return;
         }
+
+        for entry in self.state.get_entries(&span) {
+            entry.track_exited(id, span.name());
+        }
+
+        // Exiting a span makes whichever span is now at the top of the stack current again, which
+        // may well be a matching span.
+        if let Some(current) = ctx.lookup_current() {
+            if self.is_tracking(&current) {
+                for entry in self.state.get_entries(&current) {
+                    entry.track_current();
+                }
+            }
+        }
+
+        #[cfg(feature = "diagnostics")]
+        self.emit_diagnostics();
     }
 
     fn on_close(&self, id: Id, ctx: Context<'_, S>) {
         let span = ctx.span(&id).expect("span must already exist!");
-        if let Some(entry) = self.state.get_entry(span) {
-            entry.track_closed();
+        if !self.is_tracking(&span) {
+            return;
+        }
+
+        for entry in self.state.get_entries(&span) {
+            entry.track_closed(&id, span.name());
+        }
+
+        // The span may have been tracked as a matching span, or as the parent of one, under
+        // entries which it no longer matches, such as when its recorded values have changed, and
+        // its ID will be reused, so it has to be forgotten everywhere.
+        self.state.forget_instance(&id);
+
+        #[cfg(feature = "backscan")]
+        self.state.track_closed_span(&id);
+
+        #[cfg(feature = "diagnostics")]
+        self.emit_diagnostics();
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        // Diagnostic events are emitted from within this layer's own callbacks, and tracking them
+        // could change whether an assertion is satisfied, which would emit yet another diagnostic
+        // event, and so on, so they are never tracked.
+        #[cfg(feature = "diagnostics")]
+        if diagnostics::is_emitting() {
+            return;
+        }
+
+        // Events are attributed to every matching span in their scope, but only once per entry,
+        // even if multiple spans in the scope match the same entry.  Events emitted outside of any
+        // span have nothing to match against, and so are never tracked.
+        let scope = match ctx.event_scope(event) {
+            Some(scope) => scope,
+            None => return,
+        };
+
+        let mut entries = Vec::new();
+        for span in scope {
+            if !self.is_tracking(&span) {
+                continue;
+            }
+
+            for entry in self.state.get_entries(&span) {
+                if !entries.iter().any(|existing| Arc::ptr_eq(existing, &entry)) {
+                    entries.push(entry);
+                }
+            }
         }
+
+        for entry in entries {
+            entry.track_event(event.metadata().level());
+        }
+
+        #[cfg(feature = "diagnostics")]
+        self.emit_diagnostics();
     }
 
     unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
@@ -64,3 +382,39 @@ where
         }
     }
 }
+
+fn store_recorded_values<S>(span: &SpanRef<'_, S>, values: &[(&'static str, FieldValueSnapshot)])
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let mut extensions = span.extensions_mut();
+    match extensions.get_mut::<RecordedValues>() {
+        Some(recorded) => recorded.update(values),
+        None => {
+            let mut recorded = RecordedValues::default();
+            recorded.update(values);
+            extensions.insert(recorded);
+        }
+    }
+}
+
+/// Describes a span in the same style as span matchers, for use in failure messages.
+fn describe_span<S>(span: &SpanRef<'_, S>) -> String
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let mut description = format!(
+        "name={:?} target={:?}",
+        span.name(),
+        span.metadata().target()
+    );
+    let fields = span
+        .fields()
+        .iter()
+        .map(|field| field.name())
+        .collect::<Vec<_>>();
+    if !fields.is_empty() {
+        description.push_str(&format!(" fields=[{}]", fields.join(",")));
+    }
+    description
+}
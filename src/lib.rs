@@ -1,7 +1,18 @@
 pub mod assertion;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
 mod layer;
+mod macros;
 mod matcher;
 mod state;
+#[cfg(test)]
+mod tests;
+mod value;
 
-pub use assertion::{Assertion, AssertionBuilder, AssertionRegistry};
+pub use assertion::{
+    Assertion, AssertionBuilder, AssertionFailure, AssertionFailures, AssertionRegistry,
+    AssertionReport, CountDelta, CriterionReport, EntryCounts, RegistrySnapshot, Stage,
+};
 pub use layer::AssertionsLayer;
+pub use matcher::SpanInfo;
+pub use value::{FieldBound, FieldType, FieldValueSnapshot};
@@ -0,0 +1,101 @@
+/// Constructs an [`Assertion`][crate::Assertion] from a concise list of span matchers and criteria.
+///
+/// This expands to the equivalent [`AssertionBuilder`][crate::AssertionBuilder] method chain,
+/// followed by `finalize`, and so follows the same rules: span matchers must come first, and at
+/// least one criterion must be given.
+///
+/// The supported span matchers are `name = ...`, `target = ...`, `parent_name = ...`, and
+/// `field = ...`, which map to `with_name`, `with_target`, `with_parent_name`, and
+/// `with_span_field`, respectively.
+///
+/// The supported criteria are `created`, `entered`, `exited`, and `closed`, each of which can be
+/// given on its own, such as `entered`, to assert that the span reached that stage at all, or
+/// followed by `== n` or `>= n` to assert that it did so exactly or at least `n` times.
+///
+/// ```rust
+/// # use tracing_fluent_assertions::{assert_span, AssertionRegistry, AssertionsLayer};
+/// # use tracing_subscriber::{layer::SubscriberExt, Registry};
+/// # let registry = AssertionRegistry::default();
+/// # let subscriber = Registry::default().with(AssertionsLayer::new(&registry));
+/// let assertion = assert_span!(registry, name = "query", target = "myapp::db", entered >= 1, closed == 1);
+///
+/// // Equivalent to:
+/// let equivalent = registry.build()
+///     .with_name("query")
+///     .with_target("myapp::db")
+///     .was_entered_at_least(1)
+///     .was_closed_exactly(1)
+///     .finalize();
+/// # tracing::subscriber::with_default(subscriber, || {
+/// #     tracing::info_span!(target: "myapp::db", "query").in_scope(|| {});
+/// # });
+/// # assertion.assert();
+/// # equivalent.assert();
+/// ```
+#[macro_export]
+macro_rules! assert_span {
+    ($registry:expr, $($rest:tt)+) => {
+        $crate::__assert_span_chain!(($registry.build()) $($rest)+)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __assert_span_chain {
+    (($builder:expr)) => {
+        $builder.finalize()
+    };
+
+    // Span matchers.
+    (($builder:expr) name = $name:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.with_name($name)) $($($rest)*)?)
+    };
+    (($builder:expr) target = $target:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.with_target($target)) $($($rest)*)?)
+    };
+    (($builder:expr) parent_name = $name:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.with_parent_name($name)) $($($rest)*)?)
+    };
+    (($builder:expr) field = $field:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.with_span_field($field)) $($($rest)*)?)
+    };
+
+    // Criteria.  The forms with a count have to come first, as otherwise the bare forms would be
+    // tried first and fail to match the rest of the input.
+    (($builder:expr) created == $n:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_created_exactly($n)) $($($rest)*)?)
+    };
+    (($builder:expr) created >= $n:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_created_at_least($n)) $($($rest)*)?)
+    };
+    (($builder:expr) created $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_created()) $($($rest)*)?)
+    };
+    (($builder:expr) entered == $n:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_entered_exactly($n)) $($($rest)*)?)
+    };
+    (($builder:expr) entered >= $n:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_entered_at_least($n)) $($($rest)*)?)
+    };
+    (($builder:expr) entered $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_entered()) $($($rest)*)?)
+    };
+    (($builder:expr) exited == $n:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_exited_exactly($n)) $($($rest)*)?)
+    };
+    (($builder:expr) exited >= $n:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_exited_at_least($n)) $($($rest)*)?)
+    };
+    (($builder:expr) exited $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_exited()) $($($rest)*)?)
+    };
+    (($builder:expr) closed == $n:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_closed_exactly($n)) $($($rest)*)?)
+    };
+    (($builder:expr) closed >= $n:expr $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_closed_at_least($n)) $($($rest)*)?)
+    };
+    (($builder:expr) closed $(, $($rest:tt)*)?) => {
+        $crate::__assert_span_chain!(($builder.was_closed()) $($($rest)*)?)
+    };
+}
@@ -1,57 +1,318 @@
-use tracing::Subscriber;
+use std::{
+    cmp::Ordering,
+    collections::BTreeSet,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Arc,
+    thread::ThreadId,
+};
+
+use tracing::{Id, Metadata, Subscriber};
 use tracing_subscriber::registry::{LookupSpan, SpanRef};
 
-#[derive(Clone, Eq, Hash, PartialEq)]
+use crate::value::{FieldType, FieldValueSnapshot, RecordedValues};
+
+/// A view of a span, as given to custom span predicates.
+pub struct SpanInfo<'a> {
+    metadata: &'static Metadata<'static>,
+    values: Option<&'a RecordedValues>,
+}
+
+impl<'a> SpanInfo<'a> {
+    /// Gets the name of the span.
+    pub fn name(&self) -> &'static str {
+        self.metadata.name()
+    }
+
+    /// Gets the target of the span.
+    pub fn target(&self) -> &'static str {
+        self.metadata.target()
+    }
+
+    /// Gets the metadata of the span.
+    pub fn metadata(&self) -> &'static Metadata<'static> {
+        self.metadata
+    }
+
+    /// Gets the most recent value recorded for the given field.
+    ///
+    /// If the field does not exist, or has not yet been recorded, `None` is returned.
+    pub fn value(&self, field: &str) -> Option<&FieldValueSnapshot> {
+        self.values.and_then(|values| values.get(field))
+    }
+}
+
+/// The thread a span was created on, stored in the span's extensions.
+pub(crate) struct CreatedOn(pub ThreadId);
+
+/// The names of the spans a span follows from, stored in the span's extensions.
+pub(crate) struct FollowsFrom(pub Vec<&'static str>);
+
+type PredicateFn = dyn Fn(&SpanInfo<'_>) -> bool + Send + Sync;
+
+/// A custom span predicate.
+///
+/// As closures cannot be compared or hashed, predicates are identified by their allocation, which
+/// means that two matchers are only equal if they share the very same predicate.
+#[derive(Clone)]
+struct Predicate(Arc<PredicateFn>);
+
+impl PartialEq for Predicate {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Predicate {}
+
+impl Hash for Predicate {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const ()).hash(state);
+    }
+}
+
+/// A compiled regular expression for matching span targets.
+///
+/// `Regex` implements neither `Eq` nor `Hash`, which span matchers need in order to be used as map
+/// keys, so the pattern itself is used for identity instead: two regular expressions compiled from
+/// the same pattern always match the same targets.
+#[cfg(feature = "regex")]
+#[derive(Clone)]
+struct TargetRegex(regex::Regex);
+
+#[cfg(feature = "regex")]
+impl PartialEq for TargetRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Eq for TargetRegex {}
+
+#[cfg(feature = "regex")]
+impl Hash for TargetRegex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_str().hash(state);
+    }
+}
+
+/// An inclusive range of numeric field values.
+///
+/// The bounds are always numeric.  As floating-point numbers are neither `Eq` nor `Hash`, the
+/// bounds are compared and hashed by their type and bit patterns.
+#[derive(Clone, Debug)]
+struct FieldRange {
+    min: FieldValueSnapshot,
+    max: FieldValueSnapshot,
+}
+
+impl FieldRange {
+    fn contains(&self, value: &FieldValueSnapshot) -> bool {
+        matches!(
+            value.compare_numeric(&self.min),
+            Some(Ordering::Greater | Ordering::Equal)
+        ) && matches!(
+            value.compare_numeric(&self.max),
+            Some(Ordering::Less | Ordering::Equal)
+        )
+    }
+
+    fn bound_bits(bound: &FieldValueSnapshot) -> (FieldType, u64) {
+        let bits = match bound {
+            FieldValueSnapshot::I64(value) => *value as u64,
+            FieldValueSnapshot::U64(value) => *value,
+            FieldValueSnapshot::F64(value) => value.to_bits(),
+            _ => unreachable!("range bounds are always numeric"),
+        };
+        (bound.field_type(), bits)
+    }
+}
+
+impl PartialEq for FieldRange {
+    fn eq(&self, other: &Self) -> bool {
+        Self::bound_bits(&self.min) == Self::bound_bits(&other.min)
+            && Self::bound_bits(&self.max) == Self::bound_bits(&other.max)
+    }
+}
+
+impl Eq for FieldRange {}
+
+impl Hash for FieldRange {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Self::bound_bits(&self.min).hash(state);
+        Self::bound_bits(&self.max).hash(state);
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 enum FieldCriterion {
     Exists(String),
+    Recorded(String),
+    DebugValue(String, String),
+    Typed(String, FieldType),
+    InRange(String, FieldRange),
+    Exactly(BTreeSet<String>),
 }
 
 #[derive(Clone, Default, Eq, Hash, PartialEq)]
 pub struct SpanMatcher {
+    id: Option<Id>,
     name: Option<String>,
+    any_names: Option<BTreeSet<String>>,
     target: Option<String>,
-    parent_name: Option<String>,
+    #[cfg(feature = "regex")]
+    target_regex: Option<TargetRegex>,
+    location: Option<(String, u32)>,
+    parent_names: Vec<String>,
+    parent_target: Option<String>,
+    follows_from_names: Vec<String>,
+    no_parent: bool,
+    thread: Option<ThreadId>,
     fields: Vec<FieldCriterion>,
+    predicates: Vec<Predicate>,
+    ignore_case: bool,
 }
 
 impl SpanMatcher {
+    pub fn set_id(&mut self, id: Id) {
+        self.id = Some(id);
+    }
+
     pub fn set_name(&mut self, name: String) {
         self.name = Some(name);
     }
 
-    pub fn set_parent_name(&mut self, name: String) {
-        self.parent_name = Some(name);
+    pub fn set_any_names(&mut self, names: BTreeSet<String>) {
+        self.any_names = Some(names);
+    }
+
+    pub fn set_location(&mut self, file: String, line: u32) {
+        self.location = Some((file, line));
+    }
+
+    pub fn add_parent_name(&mut self, name: String) {
+        self.parent_names.push(name);
+    }
+
+    pub fn set_parent_target(&mut self, target: String) {
+        self.parent_target = Some(target);
+    }
+
+    pub fn add_follows_from_name(&mut self, name: String) {
+        self.follows_from_names.push(name);
+    }
+
+    pub fn set_no_parent(&mut self) {
+        self.no_parent = true;
     }
 
     pub fn set_target(&mut self, target: String) {
         self.target = Some(target);
     }
 
+    #[cfg(feature = "regex")]
+    pub fn set_target_regex(&mut self, regex: regex::Regex) {
+        self.target_regex = Some(TargetRegex(regex));
+    }
+
+    pub fn set_thread(&mut self, thread: ThreadId) {
+        self.thread = Some(thread);
+    }
+
+    pub fn set_ignore_case(&mut self) {
+        self.ignore_case = true;
+    }
+
     pub fn add_field_exists(&mut self, field: String) {
         self.fields.push(FieldCriterion::Exists(field));
     }
 
+    pub fn add_field_recorded(&mut self, field: String) {
+        self.fields.push(FieldCriterion::Recorded(field));
+    }
+
+    pub fn add_field_debug_value(&mut self, field: String, expected: String) {
+        self.fields
+            .push(FieldCriterion::DebugValue(field, expected));
+    }
+
+    pub fn add_field_typed(&mut self, field: String, field_type: FieldType) {
+        self.fields.push(FieldCriterion::Typed(field, field_type));
+    }
+
+    pub fn add_field_in_range(
+        &mut self,
+        field: String,
+        min: FieldValueSnapshot,
+        max: FieldValueSnapshot,
+    ) {
+        self.fields
+            .push(FieldCriterion::InRange(field, FieldRange { min, max }));
+    }
+
+    pub fn add_exact_fields(&mut self, fields: BTreeSet<String>) {
+        self.fields.push(FieldCriterion::Exactly(fields));
+    }
+
+    pub fn add_predicate<F>(&mut self, predicate: F)
+    where
+        F: Fn(&SpanInfo<'_>) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Predicate(Arc::new(predicate)));
+    }
+
     pub fn matches<S>(&self, span: &SpanRef<'_, S>) -> bool
     where
         S: Subscriber + for<'a> LookupSpan<'a>,
     {
+        if let Some(id) = self.id.as_ref() {
+            if span.id() != *id {
+                return false;
+            }
+        }
+
         if let Some(name) = self.name.as_ref() {
-            if span.name() != name {
+            if !self.str_eq(span.name(), name) {
+                return false;
+            }
+        }
+
+        if let Some(names) = self.any_names.as_ref() {
+            if !names.iter().any(|name| self.str_eq(span.name(), name)) {
                 return false;
             }
         }
 
         if let Some(target) = self.target.as_ref() {
-            if span.metadata().target() != target {
+            if !self.str_eq(span.metadata().target(), target) {
+                return false;
+            }
+        }
+
+        #[cfg(feature = "regex")]
+        if let Some(TargetRegex(regex)) = self.target_regex.as_ref() {
+            if !regex.is_match(span.metadata().target()) {
+                return false;
+            }
+        }
+
+        // Spans whose metadata has no location can't be from the expected location, so they never
+        // match.
+        if let Some((file, line)) = self.location.as_ref() {
+            let metadata = span.metadata();
+            if metadata.file() != Some(file.as_str()) || metadata.line() != Some(*line) {
                 return false;
             }
         }
 
-        if let Some(name) = self.parent_name.as_ref() {
+        // Each parent name only has to be found somewhere in the lineage, independently of the
+        // others.
+        for name in &self.parent_names {
             let mut has_matching_parent = false;
             let mut parent = span.parent();
             while let Some(span) = parent {
-                if span.name() == name {
+                if self.str_eq(span.name(), name) {
                     has_matching_parent = true;
                     break;
                 }
@@ -64,6 +325,52 @@ impl SpanMatcher {
             }
         }
 
+        if let Some(target) = self.parent_target.as_ref() {
+            let mut has_matching_parent = false;
+            let mut parent = span.parent();
+            while let Some(span) = parent {
+                if self.str_eq(span.metadata().target(), target) {
+                    has_matching_parent = true;
+                    break;
+                }
+
+                parent = span.parent();
+            }
+
+            if !has_matching_parent {
+                return false;
+            }
+        }
+
+        if !self.follows_from_names.is_empty() {
+            let extensions = span.extensions();
+            let follows_from = match extensions.get::<FollowsFrom>() {
+                Some(FollowsFrom(follows_from)) => follows_from.as_slice(),
+                None => &[],
+            };
+
+            for name in &self.follows_from_names {
+                if !follows_from
+                    .iter()
+                    .any(|follows_from| self.str_eq(follows_from, name))
+                {
+                    return false;
+                }
+            }
+        }
+
+        if self.no_parent && span.parent().is_some() {
+            return false;
+        }
+
+        if let Some(thread) = self.thread.as_ref() {
+            let extensions = span.extensions();
+            match extensions.get::<CreatedOn>() {
+                Some(CreatedOn(created_on)) if created_on == thread => {}
+                _ => return false,
+            }
+        }
+
         if !self.fields.is_empty() {
             let span_fields = span.fields();
             for field in &self.fields {
@@ -73,10 +380,213 @@ impl SpanMatcher {
                             return false;
                         }
                     }
+                    FieldCriterion::Recorded(expected_field) => {
+                        let extensions = span.extensions();
+                        let recorded = extensions
+                            .get::<RecordedValues>()
+                            .and_then(|recorded| recorded.get(expected_field));
+                        if recorded.is_none() {
+                            return false;
+                        }
+                    }
+                    FieldCriterion::DebugValue(expected_field, expected_value) => {
+                        let extensions = span.extensions();
+                        let recorded = extensions
+                            .get::<RecordedValues>()
+                            .and_then(|recorded| recorded.get(expected_field));
+                        match recorded {
+                            Some(value) if value.to_debug_string() == *expected_value => {}
+                            _ => return false,
+                        }
+                    }
+                    FieldCriterion::Typed(expected_field, expected_type) => {
+                        let extensions = span.extensions();
+                        let recorded = extensions
+                            .get::<RecordedValues>()
+                            .and_then(|recorded| recorded.get(expected_field));
+                        match recorded {
+                            Some(value) if value.field_type() == *expected_type => {}
+                            _ => return false,
+                        }
+                    }
+                    FieldCriterion::InRange(expected_field, range) => {
+                        let extensions = span.extensions();
+                        let recorded = extensions
+                            .get::<RecordedValues>()
+                            .and_then(|recorded| recorded.get(expected_field));
+                        match recorded {
+                            Some(value) if range.contains(value) => {}
+                            _ => return false,
+                        }
+                    }
+                    FieldCriterion::Exactly(expected_fields) => {
+                        // A span's field set includes every field declared for the span, whether
+                        // or not a value was ever recorded for it.
+                        if span_fields.len() != expected_fields.len()
+                            || !span_fields
+                                .iter()
+                                .all(|field| expected_fields.contains(field.name()))
+                        {
+                            return false;
+                        }
+                    }
                 }
             }
         }
 
+        if !self.predicates.is_empty() {
+            let extensions = span.extensions();
+            let info = SpanInfo {
+                metadata: span.metadata(),
+                values: extensions.get::<RecordedValues>(),
+            };
+            if !self.predicates.iter().all(|predicate| (predicate.0)(&info)) {
+                return false;
+            }
+        }
+
         true
     }
+
+    /// Describes the span matcher, such as `name="query" target="myapp::db" field:user_id`.
+    ///
+    /// Only the criteria which are actually set are included, always in the same order, so that
+    /// the same matcher is always described the same way.  This is the description used anywhere a
+    /// span matcher is shown, such as in failure messages and registry snapshots.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(id) = self.id.as_ref() {
+            parts.push(format!("id={}", id.into_u64()));
+        }
+        if let Some(name) = self.name.as_ref() {
+            parts.push(format!("name={:?}", name));
+        }
+        if let Some(names) = self.any_names.as_ref() {
+            let names = names
+                .iter()
+                .map(|name| format!("{:?}", name))
+                .collect::<Vec<_>>();
+            parts.push(format!("name=[{}]", names.join("|")));
+        }
+        if let Some(target) = self.target.as_ref() {
+            parts.push(format!("target={:?}", target));
+        }
+        #[cfg(feature = "regex")]
+        if let Some(TargetRegex(regex)) = self.target_regex.as_ref() {
+            parts.push(format!("target~={:?}", regex.as_str()));
+        }
+        if let Some((file, line)) = self.location.as_ref() {
+            parts.push(format!("location={:?}:{}", file, line));
+        }
+        for name in &self.parent_names {
+            parts.push(format!("parent={:?}", name));
+        }
+        if let Some(target) = self.parent_target.as_ref() {
+            parts.push(format!("parent_target={:?}", target));
+        }
+        for name in &self.follows_from_names {
+            parts.push(format!("follows_from={:?}", name));
+        }
+        if self.no_parent {
+            parts.push("parent=none".to_string());
+        }
+        if let Some(thread) = self.thread.as_ref() {
+            parts.push(format!("thread={:?}", thread));
+        }
+        for field in &self.fields {
+            match field {
+                FieldCriterion::Exists(name) => parts.push(format!("field:{}", name)),
+                FieldCriterion::Recorded(name) => parts.push(format!("recorded_field:{}", name)),
+                FieldCriterion::DebugValue(name, value) => {
+                    parts.push(format!("field:{}={:?}", name, value))
+                }
+                FieldCriterion::Typed(name, field_type) => {
+                    parts.push(format!("field:{}:{}", name, field_type))
+                }
+                FieldCriterion::InRange(name, range) => parts.push(format!(
+                    "field:{}={}..={}",
+                    name,
+                    range.min.to_debug_string(),
+                    range.max.to_debug_string()
+                )),
+                FieldCriterion::Exactly(names) => {
+                    let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+                    parts.push(format!("fields=[{}]", names.join(",")));
+                }
+            }
+        }
+        if !self.predicates.is_empty() {
+            parts.push(format!("predicates={}", self.predicates.len()));
+        }
+        if self.ignore_case {
+            parts.push("ignore_case".to_string());
+        }
+
+        parts.join(" ")
+    }
+
+    fn str_eq(&self, actual: &str, expected: &str) -> bool {
+        if self.ignore_case {
+            actual.eq_ignore_ascii_case(expected)
+        } else {
+            actual == expected
+        }
+    }
+}
+
+impl fmt::Debug for SpanMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Only the criteria which are actually set are included, to keep the output compact.
+        let mut debug = f.debug_struct("SpanMatcher");
+        if let Some(id) = self.id.as_ref() {
+            debug.field("id", &id.into_u64());
+        }
+        if let Some(name) = self.name.as_ref() {
+            debug.field("name", name);
+        }
+        if let Some(names) = self.any_names.as_ref() {
+            debug.field("any_names", names);
+        }
+        if let Some(target) = self.target.as_ref() {
+            debug.field("target", target);
+        }
+        #[cfg(feature = "regex")]
+        if let Some(TargetRegex(regex)) = self.target_regex.as_ref() {
+            debug.field("target_regex", &regex.as_str());
+        }
+        if let Some(location) = self.location.as_ref() {
+            debug.field("location", location);
+        }
+        if !self.parent_names.is_empty() {
+            debug.field("parent_names", &self.parent_names);
+        }
+        if let Some(target) = self.parent_target.as_ref() {
+            debug.field("parent_target", target);
+        }
+        if !self.follows_from_names.is_empty() {
+            debug.field("follows_from_names", &self.follows_from_names);
+        }
+        if self.no_parent {
+            debug.field("no_parent", &self.no_parent);
+        }
+        if let Some(thread) = self.thread.as_ref() {
+            debug.field("thread", thread);
+        }
+        if !self.fields.is_empty() {
+            debug.field("fields", &self.fields);
+        }
+        if !self.predicates.is_empty() {
+            debug.field("predicates", &self.predicates.len());
+        }
+        if self.ignore_case {
+            debug.field("ignore_case", &self.ignore_case);
+        }
+        debug.finish()
+    }
+}
+
+impl fmt::Display for SpanMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.describe())
+    }
 }
@@ -1,15 +1,89 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
     },
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
 };
 
-use tracing::Subscriber;
+use tracing::{Id, Level, Subscriber};
 use tracing_subscriber::registry::{LookupSpan, SpanRef};
 
-use crate::matcher::SpanMatcher;
+#[cfg(feature = "diagnostics")]
+use crate::diagnostics::{self, Transition};
+use crate::{
+    assertion::{AssertionCriterion, EntryCounts, Stage},
+    matcher::SpanMatcher,
+    value::FieldValueSnapshot,
+};
+
+/// State tracked for a single instance of a matching span, keyed by its span ID.
+#[derive(Default)]
+struct InstanceState {
+    created: bool,
+    entered: bool,
+    entered_again: bool,
+    reentered: bool,
+    had_children: bool,
+    backscanned: bool,
+    depth_tracked: bool,
+    depth: usize,
+    entered_at: Option<Instant>,
+    created_at: Option<Instant>,
+    busy: Duration,
+}
+
+/// The sequence number given to the next entry to have a matching span entered for the first time.
+///
+/// `Instant` has a limited resolution, so two entries can easily have identical first-entered
+/// instants, which makes it useless for telling which was entered first.
+static NEXT_ENTERED_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// The maximum number of stages recorded in the lifecycle trace of a single span instance.
+///
+/// Spans which are entered and exited in a loop would otherwise grow their trace without bound.
+const MAX_LIFECYCLE_STAGES: usize = 64;
+
+/// The maximum number of values kept for a single field across all matching spans.
+///
+/// Spans created in a loop under a long-lived registry would otherwise grow the recorded values
+/// without bound, so only the most recently recorded values are kept.
+pub(crate) const MAX_RECORDED_VALUES: usize = 1024;
+
+/// The maximum number of matching span IDs kept.
+///
+/// As with recorded values, only the IDs of the most recently created matching spans are kept.
+pub(crate) const MAX_MATCHED_IDS: usize = 1024;
+
+/// The ordered lifecycle stages of a single instance of a matching span.
+#[derive(Default)]
+struct LifecycleTrace {
+    stages: Vec<Stage>,
+    closed: bool,
+    truncated: bool,
+}
+
+impl LifecycleTrace {
+    fn push(&mut self, stage: Stage) {
+        if self.stages.len() < MAX_LIFECYCLE_STAGES {
+            self.stages.push(stage);
+        } else {
+            self.truncated = true;
+        }
+    }
+}
+
+/// The busy and total durations of matching spans which have closed.
+///
+/// These are kept together so that the idle duration, which is the difference between the two, is
+/// always calculated from a consistent view of both.
+#[derive(Default)]
+struct ClosedDurations {
+    busy: Duration,
+    total: Duration,
+}
 
 #[derive(Default)]
 pub(crate) struct EntryState {
@@ -17,23 +91,579 @@ pub(crate) struct EntryState {
     entered: AtomicUsize,
     exited: AtomicUsize,
     closed: AtomicUsize,
+    distinct_entered: AtomicUsize,
+    closed_never_entered: AtomicUsize,
+    entered_more_than_once: AtomicUsize,
+    reentered: AtomicUsize,
+    current: AtomicUsize,
+    over_exited: AtomicBool,
+    parents: AtomicUsize,
+    max_depth: AtomicUsize,
+    depth_range: Mutex<Option<(usize, usize)>>,
+    events: AtomicUsize,
+    events_by_level: [AtomicUsize; 5],
+    first_entered: Mutex<Option<(Instant, u64)>>,
+    threads: Mutex<HashSet<ThreadId>>,
+    instances: Mutex<HashMap<u64, InstanceState>>,
+    lifecycles: Mutex<HashMap<u64, LifecycleTrace>>,
+    matched_ids: Mutex<VecDeque<u64>>,
+    active: Mutex<Duration>,
+    closed_durations: Mutex<ClosedDurations>,
+    values: Mutex<HashMap<&'static str, VecDeque<FieldValueSnapshot>>>,
+    record_counts: Mutex<HashMap<&'static str, usize>>,
+    by_name: Mutex<HashMap<&'static str, EntryCounts>>,
+    generation: Mutex<u64>,
+    changed: Condvar,
+    capture_offenders: AtomicBool,
+    first_created_span: Mutex<Option<String>>,
+    first_entered_span: Mutex<Option<String>>,
+    fail_fast_created: Mutex<Vec<(u64, String)>>,
+    fail_fast_entered: Mutex<Vec<(u64, String)>>,
 }
 
 impl EntryState {
-    pub fn track_created(&self) {
+    pub fn track_created(&self, id: &Id, name: &'static str) {
+        self.with_name_counts(name, |counts| counts.created += 1);
+
+        let mut instances = self
+            .instances
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        let instance = instances.entry(id.into_u64()).or_default();
+        instance.created = true;
+        instance.depth_tracked = true;
+        instance.created_at = Some(Instant::now());
+        drop(instances);
+
+        // A span with the same ID as an earlier span is a new instance, so it gets a fresh trace.
+        let mut trace = LifecycleTrace::default();
+        trace.push(Stage::Created);
+        self.lifecycles
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .insert(id.into_u64(), trace);
+
+        let mut matched_ids = self
+            .matched_ids
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        if matched_ids.len() == MAX_MATCHED_IDS {
+            matched_ids.pop_front();
+        }
+        matched_ids.push_back(id.into_u64());
+        drop(matched_ids);
+
         self.created.fetch_add(1, Ordering::AcqRel);
+
+        self.notify_changed();
     }
 
-    pub fn track_entered(&self) {
+    /// Tracks an already-open matching span found by a backscan as being created.
+    ///
+    /// Whether the span is currently entered can't be known, so it is never considered to have
+    /// been exited more times than it was entered.
+    #[cfg(feature = "backscan")]
+    pub fn track_backscanned(&self, id: &Id, name: &'static str) {
+        self.track_created(id, name);
+
+        if let Some(instance) = self
+            .instances
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .get_mut(&id.into_u64())
+        {
+            instance.backscanned = true;
+        }
+    }
+
+    pub fn track_entered(&self, id: &Id, name: &'static str) {
+        self.with_name_counts(name, |counts| counts.entered += 1);
+
+        let now = Instant::now();
+        let mut first_entered = self
+            .first_entered
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        first_entered
+            .get_or_insert_with(|| (now, NEXT_ENTERED_SEQUENCE.fetch_add(1, Ordering::AcqRel)));
+        drop(first_entered);
+
+        self.threads
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .insert(thread::current().id());
+
+        // A span can be entered again before it has exited, so we only start an interval when the
+        // span goes from not being entered at all to being entered.
+        let mut instances = self
+            .instances
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        let instance = instances.entry(id.into_u64()).or_default();
+        instance.depth_tracked = true;
+        if !instance.entered {
+            instance.entered = true;
+            self.distinct_entered.fetch_add(1, Ordering::AcqRel);
+        } else if !instance.entered_again {
+            instance.entered_again = true;
+            self.entered_more_than_once.fetch_add(1, Ordering::AcqRel);
+        }
+        if instance.depth == 0 {
+            instance.entered_at = Some(now);
+        } else if !instance.reentered {
+            // Being entered while still entered is what makes a span reentered, as opposed to simply
+            // being entered again after having exited.
+            instance.reentered = true;
+            self.reentered.fetch_add(1, Ordering::AcqRel);
+        }
+        instance.depth += 1;
+        drop(instances);
+
+        self.track_lifecycle(id, Stage::Entered);
+
         self.entered.fetch_add(1, Ordering::AcqRel);
+
+        self.notify_changed();
     }
 
-    pub fn track_exited(&self) {
+    pub fn track_exited(&self, id: &Id, name: &'static str) {
+        self.with_name_counts(name, |counts| counts.exited += 1);
+
+        let now = Instant::now();
+        let mut instances = self
+            .instances
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        if let Some(instance) = instances.get_mut(&id.into_u64()) {
+            // This has to be checked as it happens, and per instance: the totals may well be
+            // balanced by the time anyone gets around to looking at them, and they can't tell a
+            // span exited too many times apart from one that was entered before it was tracked.
+            // Spans found by a backscan may have been entered before they were tracked, so their
+            // depth can't be relied upon, and neither can the depth of spans we've only seen
+            // become a parent.
+            if instance.depth == 0 && instance.depth_tracked && !instance.backscanned {
+                self.over_exited.store(true, Ordering::Release);
+            }
+
+            instance.depth = instance.depth.saturating_sub(1);
+            if instance.depth == 0 {
+                if let Some(entered_at) = instance.entered_at.take() {
+                    let busy = now.saturating_duration_since(entered_at);
+                    instance.busy += busy;
+
+                    let mut active = self
+                        .active
+                        .lock()
+                        .expect("i literally don't know what a poisoned thread is");
+                    *active += busy;
+                }
+            }
+        }
+        drop(instances);
+
+        self.track_lifecycle(id, Stage::Exited);
+
         self.exited.fetch_add(1, Ordering::AcqRel);
+
+        self.notify_changed();
     }
 
-    pub fn track_closed(&self) {
+    /// Tracks a matching span being closed.
+    ///
+    /// Per-instance state is removed separately, via [`forget_instance`], as it has to be removed
+    /// from every entry, not just the entry the span matched when it closed.
+    pub fn track_closed(&self, id: &Id, name: &'static str) {
+        self.with_name_counts(name, |counts| counts.closed += 1);
+
+        self.track_lifecycle(id, Stage::Closed);
+
         self.closed.fetch_add(1, Ordering::AcqRel);
+
+        self.notify_changed();
+    }
+
+    /// Records a lifecycle stage in the trace of the given span instance.
+    ///
+    /// Traces are kept after the span closes, so that they can be inspected afterwards, which means
+    /// that the trace for an ID may belong to an earlier span that was given the same ID.  Spans
+    /// only start a fresh trace when they're created, so a span which didn't match when it was
+    /// created starts a fresh trace when any other stage is recorded for it instead.
+    fn track_lifecycle(&self, id: &Id, stage: Stage) {
+        let mut lifecycles = self
+            .lifecycles
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        let trace = lifecycles.entry(id.into_u64()).or_default();
+        if trace.closed {
+            *trace = LifecycleTrace::default();
+        }
+        trace.push(stage);
+        trace.closed = stage == Stage::Closed;
+    }
+
+    /// Forgets any state tracked for the given span instance.
+    ///
+    /// Span IDs are reused once a span has closed, so this must be called whenever a span closes,
+    /// whether or not it still matches, so that a new span given the same ID starts fresh.
+    pub fn forget_instance(&self, id: &Id) {
+        let instance = self
+            .instances
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .remove(&id.into_u64());
+        if let Some(instance) = instance {
+            if instance.created && !instance.entered {
+                self.closed_never_entered.fetch_add(1, Ordering::AcqRel);
+            }
+
+            // Only spans which were tracked from the moment they were created have a known total
+            // duration, and so only they contribute to the idle duration.
+            if let Some(created_at) = instance.created_at {
+                let mut closed_durations = self
+                    .closed_durations
+                    .lock()
+                    .expect("i literally don't know what a poisoned thread is");
+                closed_durations.busy += instance.busy;
+                closed_durations.total += created_at.elapsed();
+            }
+        }
+    }
+
+    /// Enables capturing a description of the first span to be created, and entered, so that
+    /// negative criteria can say which span violated them.
+    pub fn capture_offenders(&self) {
+        self.capture_offenders.store(true, Ordering::Release);
+    }
+
+    /// Captures a description of a created span, if enabled and none has been captured yet.
+    ///
+    /// The description is only generated if it will actually be captured.
+    pub fn track_created_offender<F>(&self, describe: F)
+    where
+        F: FnOnce() -> String,
+    {
+        self.track_offender(&self.first_created_span, describe);
+    }
+
+    /// Captures a description of an entered span, if enabled and none has been captured yet.
+    ///
+    /// The description is only generated if it will actually be captured.
+    pub fn track_entered_offender<F>(&self, describe: F)
+    where
+        F: FnOnce() -> String,
+    {
+        self.track_offender(&self.first_entered_span, describe);
+    }
+
+    /// Makes creating a matching span panic immediately with the given message, for as long as the
+    /// given assertion exists.
+    pub fn fail_fast_on_created(&self, assertion_id: u64, message: String) {
+        self.fail_fast_created
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .push((assertion_id, message));
+    }
+
+    /// Makes entering a matching span panic immediately with the given message, for as long as the
+    /// given assertion exists.
+    pub fn fail_fast_on_entered(&self, assertion_id: u64, message: String) {
+        self.fail_fast_entered
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .push((assertion_id, message));
+    }
+
+    /// Stops matching spans from failing fast on behalf of the given assertion.
+    ///
+    /// Other assertions sharing this entry may have asked to fail fast as well, so matching spans
+    /// only stop failing fast once every one of them has been dropped.
+    pub fn clear_fail_fast(&self, assertion_id: u64) {
+        for slot in [&self.fail_fast_created, &self.fail_fast_entered] {
+            slot.lock()
+                .expect("i literally don't know what a poisoned thread is")
+                .retain(|(id, _)| *id != assertion_id);
+        }
+    }
+
+    /// Panics if creating a matching span should fail fast, describing the span in the message.
+    pub fn check_fail_fast_created<F>(&self, describe: F)
+    where
+        F: FnOnce() -> String,
+    {
+        Self::check_fail_fast(&self.fail_fast_created, describe);
+    }
+
+    /// Panics if entering a matching span should fail fast, describing the span in the message.
+    pub fn check_fail_fast_entered<F>(&self, describe: F)
+    where
+        F: FnOnce() -> String,
+    {
+        Self::check_fail_fast(&self.fail_fast_entered, describe);
+    }
+
+    fn check_fail_fast<F>(slot: &Mutex<Vec<(u64, String)>>, describe: F)
+    where
+        F: FnOnce() -> String,
+    {
+        // The lock can't be held while panicking, as that would poison it.
+        let message = slot
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .first()
+            .map(|(_, message)| message.clone());
+        if let Some(message) = message {
+            panic!("{}: {}", message, describe());
+        }
+    }
+
+    fn track_offender<F>(&self, slot: &Mutex<Option<String>>, describe: F)
+    where
+        F: FnOnce() -> String,
+    {
+        if !self.capture_offenders.load(Ordering::Acquire) {
+            return;
+        }
+
+        let mut slot = slot
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        if slot.is_none() {
+            *slot = Some(describe());
+        }
+    }
+
+    fn notify_changed(&self) {
+        let mut generation = self
+            .generation
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        *generation = generation.wrapping_add(1);
+        self.changed.notify_all();
+    }
+
+    /// Gets the current generation, which changes every time anything is tracked.
+    pub fn generation(&self) -> u64 {
+        *self
+            .generation
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+    }
+
+    /// Waits until the generation differs from `last`, or until `timeout` elapses, returning the
+    /// generation at that point.
+    pub fn wait_for_change(&self, last: u64, timeout: Duration) -> u64 {
+        let generation = self
+            .generation
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        let (generation, _) = self
+            .changed
+            .wait_timeout_while(generation, timeout, |generation| *generation == last)
+            .expect("i literally don't know what a poisoned thread is");
+        *generation
+    }
+
+    fn with_name_counts<F>(&self, name: &'static str, f: F)
+    where
+        F: FnOnce(&mut EntryCounts),
+    {
+        let mut by_name = self
+            .by_name
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        f(by_name.entry(name).or_default());
+    }
+
+    pub fn track_current(&self) {
+        self.current.fetch_add(1, Ordering::AcqRel);
+
+        self.notify_changed();
+    }
+
+    /// Tracks values being recorded on a matching span after it was created.
+    pub fn track_recorded(&self, recorded: &[(&'static str, FieldValueSnapshot)]) {
+        let mut record_counts = self
+            .record_counts
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        for (field, _) in recorded {
+            *record_counts.entry(*field).or_default() += 1;
+        }
+        drop(record_counts);
+
+        self.notify_changed();
+    }
+
+    pub fn track_child(&self, parent: &Id) {
+        let mut instances = self
+            .instances
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        let instance = instances.entry(parent.into_u64()).or_default();
+        if !instance.had_children {
+            instance.had_children = true;
+            self.parents.fetch_add(1, Ordering::AcqRel);
+        }
+
+        self.notify_changed();
+    }
+
+    pub fn track_depth(&self, depth: usize) {
+        self.max_depth.fetch_max(depth, Ordering::AcqRel);
+
+        let mut depth_range = self
+            .depth_range
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        *depth_range = match *depth_range {
+            Some((min, max)) => Some((min.min(depth), max.max(depth))),
+            None => Some((depth, depth)),
+        };
+        drop(depth_range);
+
+        self.notify_changed();
+    }
+
+    pub fn track_event(&self, level: &Level) {
+        self.events.fetch_add(1, Ordering::AcqRel);
+        self.events_by_level[level_index(level)].fetch_add(1, Ordering::AcqRel);
+
+        self.notify_changed();
+    }
+
+    pub fn track_values(&self, recorded: &[(&'static str, FieldValueSnapshot)]) {
+        let mut values = self
+            .values
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        for (field, value) in recorded {
+            let values = values.entry(field).or_default();
+            if values.len() == MAX_RECORDED_VALUES {
+                values.pop_front();
+            }
+            values.push_back(value.clone());
+        }
+
+        self.notify_changed();
+    }
+
+    pub fn reset(&self) {
+        let now = Instant::now();
+
+        self.created.store(0, Ordering::Release);
+        self.entered.store(0, Ordering::Release);
+        self.exited.store(0, Ordering::Release);
+        self.closed.store(0, Ordering::Release);
+        self.distinct_entered.store(0, Ordering::Release);
+        self.closed_never_entered.store(0, Ordering::Release);
+        self.entered_more_than_once.store(0, Ordering::Release);
+        self.reentered.store(0, Ordering::Release);
+        self.current.store(0, Ordering::Release);
+        self.over_exited.store(false, Ordering::Release);
+        self.parents.store(0, Ordering::Release);
+        self.max_depth.store(0, Ordering::Release);
+        *self
+            .depth_range
+            .lock()
+            .expect("i literally don't know what a poisoned thread is") = None;
+        self.events.store(0, Ordering::Release);
+        for events in &self.events_by_level {
+            events.store(0, Ordering::Release);
+        }
+
+        *self
+            .first_entered
+            .lock()
+            .expect("i literally don't know what a poisoned thread is") = None;
+        self.threads
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .clear();
+        *self
+            .active
+            .lock()
+            .expect("i literally don't know what a poisoned thread is") = Duration::ZERO;
+        *self
+            .closed_durations
+            .lock()
+            .expect("i literally don't know what a poisoned thread is") =
+            ClosedDurations::default();
+        self.matched_ids
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .clear();
+        self.values
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .clear();
+        self.by_name
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .clear();
+        self.record_counts
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .clear();
+        self.lifecycles
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .clear();
+        *self
+            .first_created_span
+            .lock()
+            .expect("i literally don't know what a poisoned thread is") = None;
+        *self
+            .first_entered_span
+            .lock()
+            .expect("i literally don't know what a poisoned thread is") = None;
+
+        // Spans which are currently open are still tracked, but only what happens to them from this
+        // point forward should count, both in terms of being entered and their active duration.
+        let mut instances = self
+            .instances
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        for instance in instances.values_mut() {
+            instance.created = false;
+            instance.entered = false;
+            instance.entered_again = false;
+            instance.reentered = false;
+            instance.had_children = false;
+            instance.busy = Duration::ZERO;
+            if let Some(entered_at) = instance.entered_at.as_mut() {
+                *entered_at = now;
+            }
+            if let Some(created_at) = instance.created_at.as_mut() {
+                *created_at = now;
+            }
+        }
+        drop(instances);
+
+        // Criteria such as `was_not_entered` can become satisfied by a reset, so anyone waiting
+        // on them needs to check again.
+        self.notify_changed();
+    }
+
+    /// Takes a snapshot of the lifecycle counts.
+    ///
+    /// The counts are read together, so that criteria comparing one count to another are checked
+    /// against a single view of the counts, but the four loads are not atomic as a group.  To
+    /// narrow the window for an inconsistent view, the counts are loaded in reverse lifecycle
+    /// order: as a span is always created before it is entered, and so on, any span counted as
+    /// having reached a later stage will also have been counted as having reached the earlier
+    /// stages by the time they are loaded.
+    pub fn snapshot(&self) -> EntryCounts {
+        let closed = self.closed.load(Ordering::Acquire);
+        let exited = self.exited.load(Ordering::Acquire);
+        let entered = self.entered.load(Ordering::Acquire);
+        let created = self.created.load(Ordering::Acquire);
+
+        EntryCounts {
+            created,
+            entered,
+            exited,
+            closed,
+        }
     }
 
     pub fn num_created(&self) -> usize {
@@ -51,34 +681,416 @@ impl EntryState {
     pub fn num_closed(&self) -> usize {
         self.closed.load(Ordering::Acquire)
     }
+
+    pub fn distinct_entered(&self) -> usize {
+        self.distinct_entered.load(Ordering::Acquire)
+    }
+
+    /// Gets the number of matching span instances which were created but never entered.
+    ///
+    /// This includes both instances which have closed without ever being entered, and instances
+    /// which are still open but have not been entered yet.
+    pub fn num_never_entered(&self) -> usize {
+        let open_never_entered = self
+            .instances
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .values()
+            .filter(|instance| instance.created && !instance.entered)
+            .count();
+        self.closed_never_entered.load(Ordering::Acquire) + open_never_entered
+    }
+
+    pub fn num_entered_more_than_once(&self) -> usize {
+        self.entered_more_than_once.load(Ordering::Acquire)
+    }
+
+    pub fn num_reentered(&self) -> usize {
+        self.reentered.load(Ordering::Acquire)
+    }
+
+    pub fn num_current(&self) -> usize {
+        self.current.load(Ordering::Acquire)
+    }
+
+    pub fn was_over_exited(&self) -> bool {
+        self.over_exited.load(Ordering::Acquire)
+    }
+
+    pub fn num_parents(&self) -> usize {
+        self.parents.load(Ordering::Acquire)
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth.load(Ordering::Acquire)
+    }
+
+    /// Gets the lifecycle trace of the most recent span instance with the given ID.
+    pub fn lifecycle_trace(&self, id: u64) -> Vec<Stage> {
+        self.lifecycles
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .get(&id)
+            .map(|trace| trace.stages.clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether any span instance has a lifecycle trace exactly matching the given stages.
+    ///
+    /// A truncated trace is missing its later stages, so it never matches, even if the stages
+    /// which were recorded are identical.
+    pub fn has_lifecycle_sequence(&self, expected: &[Stage]) -> bool {
+        self.lifecycles
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .values()
+            .any(|trace| !trace.truncated && trace.stages == expected)
+    }
+
+    /// Gets the minimum and maximum depth of matching spans, if any have been created.
+    pub fn depth_range(&self) -> Option<(usize, usize)> {
+        *self
+            .depth_range
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+    }
+
+    pub fn first_entered_at(&self) -> Option<Instant> {
+        self.first_entered
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .map(|(entered_at, _)| entered_at)
+    }
+
+    /// Gets the sequence number of the first time a matching span was entered.
+    ///
+    /// Sequence numbers are shared by all entries, so they can be compared across entries to tell
+    /// which was entered first, even when the instants they were entered at are identical.
+    pub fn first_entered_sequence(&self) -> Option<u64> {
+        self.first_entered
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .map(|(_, sequence)| sequence)
+    }
+
+    pub fn distinct_threads(&self) -> usize {
+        self.threads
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .len()
+    }
+
+    pub fn total_duration(&self) -> Duration {
+        *self
+            .active
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+    }
+
+    /// Gets the idle duration of matching spans which have closed.
+    ///
+    /// This is the time between each span being created and closed, less the time it was busy.
+    pub fn idle_duration(&self) -> Duration {
+        let closed_durations = self
+            .closed_durations
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        closed_durations.total.saturating_sub(closed_durations.busy)
+    }
+
+    pub fn num_events(&self) -> usize {
+        self.events.load(Ordering::Acquire)
+    }
+
+    pub fn num_events_with_level(&self, level: &Level) -> usize {
+        self.events_by_level[level_index(level)].load(Ordering::Acquire)
+    }
+
+    /// Whether the given open span has been tracked as created.
+    #[cfg(feature = "backscan")]
+    pub fn has_tracked_created(&self, id: &Id) -> bool {
+        self.instances
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .get(&id.into_u64())
+            .is_some_and(|instance| instance.created)
+    }
+
+    pub fn matched_ids(&self) -> Vec<u64> {
+        self.matched_ids
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    pub fn recorded_values(&self, field: &str) -> Vec<FieldValueSnapshot> {
+        let values = self
+            .values
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        values
+            .get(field)
+            .map(|values| values.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn first_created_span(&self) -> Option<String> {
+        self.first_created_span
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .clone()
+    }
+
+    pub fn first_entered_span(&self) -> Option<String> {
+        self.first_entered_span
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .clone()
+    }
+
+    pub fn num_recorded(&self, field: &str) -> usize {
+        let record_counts = self
+            .record_counts
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        record_counts.get(field).copied().unwrap_or(0)
+    }
+
+    pub fn counts_by_name(&self) -> HashMap<String, EntryCounts> {
+        let by_name = self
+            .by_name
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        by_name
+            .iter()
+            .map(|(name, counts)| (name.to_string(), *counts))
+            .collect()
+    }
+}
+
+fn level_index(level: &Level) -> usize {
+    match *level {
+        Level::TRACE => 0,
+        Level::DEBUG => 1,
+        Level::INFO => 2,
+        Level::WARN => 3,
+        Level::ERROR => 4,
+    }
+}
+
+/// The state for a span matcher, shared by every assertion using that matcher.
+struct Entry {
+    state: Arc<EntryState>,
+    assertions: usize,
+    sequence: u64,
+}
+
+/// A live assertion, tracked so that the registry can report on all of its assertions.
+pub(crate) struct Registration {
+    pub matcher: SpanMatcher,
+    pub label: Option<String>,
+    pub criteria: Arc<Vec<AssertionCriterion>>,
+    pub entry_state: Arc<EntryState>,
+    #[cfg(feature = "diagnostics")]
+    pub satisfied: bool,
 }
 
 #[derive(Default)]
 pub(crate) struct State {
-    entries: Mutex<HashMap<SpanMatcher, Arc<EntryState>>>,
+    entries: Mutex<HashMap<SpanMatcher, Entry>>,
+    assertions: Mutex<BTreeMap<u64, Registration>>,
+    next_assertion_id: AtomicU64,
+    next_entry_sequence: AtomicU64,
+    #[cfg(feature = "backscan")]
+    open_spans: Mutex<HashMap<u64, &'static tracing::Metadata<'static>>>,
 }
 
 impl State {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::with_capacity(capacity)),
+            ..Default::default()
+        }
+    }
+
     pub fn create_entry(&self, matcher: SpanMatcher) -> Arc<EntryState> {
         let mut entries = self
             .entries
             .lock()
             .expect("i literally don't know what a poisoned thread is");
-        let entry = entries
-            .entry(matcher)
-            .or_insert_with(|| Arc::new(EntryState::default()));
-        Arc::clone(entry)
+        let entry = entries.entry(matcher).or_insert_with(|| Entry {
+            state: Arc::new(EntryState::default()),
+            assertions: 0,
+            sequence: self.next_entry_sequence.fetch_add(1, Ordering::Relaxed),
+        });
+        entry.assertions += 1;
+        Arc::clone(&entry.state)
+    }
+
+    pub fn remove_entry(&self, matcher: &SpanMatcher, state: &Arc<EntryState>) {
+        let mut entries = self
+            .entries
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+
+        // The entry may have already been cleared, and potentially even replaced by a new entry for
+        // the same matcher, so we only touch it if it's still the one we were given.
+        if let Some(entry) = entries.get_mut(matcher) {
+            if Arc::ptr_eq(&entry.state, state) {
+                entry.assertions -= 1;
+                if entry.assertions == 0 {
+                    entries.remove(matcher);
+                }
+            }
+        }
+    }
+
+    pub fn register_assertion(&self, registration: Registration) -> u64 {
+        let id = self.next_assertion_id.fetch_add(1, Ordering::Relaxed);
+        let mut assertions = self
+            .assertions
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        assertions.insert(id, registration);
+        id
+    }
+
+    pub fn deregister_assertion(&self, id: u64) {
+        let mut assertions = self
+            .assertions
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        assertions.remove(&id);
+    }
+
+    pub fn with_assertions<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&BTreeMap<u64, Registration>) -> T,
+    {
+        let assertions = self
+            .assertions
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        f(&assertions)
+    }
+
+    /// Queues a diagnostic event for every assertion whose criteria have gone from being satisfied
+    /// to violated, or vice versa, since the last time this was called.
+    #[cfg(feature = "diagnostics")]
+    pub fn check_transitions(&self) {
+        let mut assertions = self
+            .assertions
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        let transitions = assertions
+            .values_mut()
+            .filter_map(|registration| {
+                let satisfied = registration
+                    .criteria
+                    .iter()
+                    .all(|criterion| criterion.try_assert(&registration.entry_state));
+                if satisfied == registration.satisfied {
+                    return None;
+                }
+
+                registration.satisfied = satisfied;
+                Some(Transition {
+                    matcher: registration.matcher.describe(),
+                    label: registration.label.clone(),
+                    satisfied,
+                    counts: registration.entry_state.snapshot(),
+                })
+            })
+            .collect();
+        drop(assertions);
+
+        diagnostics::queue(transitions);
+    }
+
+    /// Gets all entries, in the order they were created.
+    pub fn entries(&self) -> Vec<(SpanMatcher, Arc<EntryState>)> {
+        let entries = self
+            .entries
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        let mut ordered = entries.iter().collect::<Vec<_>>();
+        ordered.sort_by_key(|(_, entry)| entry.sequence);
+        ordered
+            .into_iter()
+            .map(|(matcher, entry)| (matcher.clone(), Arc::clone(&entry.state)))
+            .collect()
+    }
+
+    pub fn get_entry_state(&self, matcher: &SpanMatcher) -> Option<Arc<EntryState>> {
+        self.entries
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .get(matcher)
+            .map(|entry| Arc::clone(&entry.state))
+    }
+
+    /// Forgets any state tracked for the given span instance across all entries.
+    pub fn forget_instance(&self, id: &Id) {
+        let entries = self
+            .entries
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        for entry in entries.values() {
+            entry.state.forget_instance(id);
+        }
     }
 
-    pub fn remove_entry(&self, matcher: &SpanMatcher) {
+    #[cfg(feature = "backscan")]
+    /// Tracks a span as being open, so that it can be found by a backscan.
+    ///
+    /// The span's metadata is kept alongside its ID so that a backscan can tell whether a span with
+    /// the same ID in a given subscriber is actually the same span.
+    pub fn track_open_span(&self, id: &Id, metadata: &'static tracing::Metadata<'static>) {
+        self.open_spans
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .insert(id.into_u64(), metadata);
+    }
+
+    #[cfg(feature = "backscan")]
+    pub fn track_closed_span(&self, id: &Id) {
+        self.open_spans
+            .lock()
+            .expect("i literally don't know what a poisoned thread is")
+            .remove(&id.into_u64());
+    }
+
+    #[cfg(feature = "backscan")]
+    pub fn open_spans(&self) -> Vec<(Id, &'static tracing::Metadata<'static>)> {
+        let open_spans = self
+            .open_spans
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        open_spans
+            .iter()
+            .map(|(id, metadata)| (Id::from_u64(*id), *metadata))
+            .collect()
+    }
+
+    pub fn clear(&self) {
         let mut entries = self
             .entries
             .lock()
             .expect("i literally don't know what a poisoned thread is");
-        entries.remove(matcher);
+        entries.clear();
+        drop(entries);
+
+        let mut assertions = self
+            .assertions
+            .lock()
+            .expect("i literally don't know what a poisoned thread is");
+        assertions.clear();
     }
 
-    pub fn get_entry<S>(&self, span: SpanRef<'_, S>) -> Option<Arc<EntryState>>
+    /// Gets the state of every entry whose span matcher matches the given span.
+    pub fn get_entries<S>(&self, span: &SpanRef<'_, S>) -> Vec<Arc<EntryState>>
     where
         S: Subscriber + for<'a> LookupSpan<'a>,
     {
@@ -88,7 +1100,8 @@ impl State {
             .expect("i literally don't know what a poisoned thread is");
         entries
             .iter()
-            .find(|(matcher, _)| matcher.matches(&span))
-            .map(|(_, state)| Arc::clone(state))
+            .filter(|(matcher, _)| matcher.matches(span))
+            .map(|(_, entry)| Arc::clone(&entry.state))
+            .collect()
     }
 }
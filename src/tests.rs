@@ -0,0 +1,1782 @@
+use std::{panic::AssertUnwindSafe, thread, time::Duration};
+
+use tracing::{Id, Level};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+use crate::{
+    matcher::SpanMatcher,
+    state::{MAX_MATCHED_IDS, MAX_RECORDED_VALUES},
+    AssertionRegistry, AssertionsLayer, EntryCounts, FieldType, FieldValueSnapshot, Stage,
+};
+
+/// Runs `f` with a subscriber made up of a registry and a single assertions layer as the default.
+fn with_registry<F>(f: F)
+where
+    F: FnOnce(&AssertionRegistry),
+{
+    let registry = AssertionRegistry::default();
+    let subscriber = Registry::default().with(AssertionsLayer::new(&registry));
+    tracing::subscriber::with_default(subscriber, || f(&registry));
+}
+
+/// Gets the message of a panic raised by `f`.
+fn panic_message<F>(f: F) -> String
+where
+    F: FnOnce(),
+{
+    let payload = std::panic::catch_unwind(AssertUnwindSafe(f)).expect_err("expected a panic");
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+#[test]
+fn assert_ordering_passes_when_entered_in_order() {
+    with_registry(|registry| {
+        let first = registry.build().with_name("first").was_entered().finalize();
+        let second = registry
+            .build()
+            .with_name("second")
+            .was_entered()
+            .finalize();
+
+        // Entering both spans back to back can leave them with identical instants, so this relies
+        // on the sequence numbers to order them.
+        tracing::info_span!("first").in_scope(|| {});
+        tracing::info_span!("second").in_scope(|| {});
+
+        registry.assert_ordering(&first, &second);
+    });
+}
+
+#[test]
+fn assert_ordering_describes_both_matchers_when_out_of_order() {
+    with_registry(|registry| {
+        let first = registry.build().with_name("first").was_entered().finalize();
+        let second = registry
+            .build()
+            .with_name("second")
+            .was_entered()
+            .describe("second span")
+            .finalize();
+
+        tracing::info_span!("second").in_scope(|| {});
+        tracing::info_span!("first").in_scope(|| {});
+
+        let message = panic_message(|| registry.assert_ordering(&first, &second));
+        assert_eq!(
+            message,
+            "assertion failed: span name=\"first\" was expected to be entered before 'second span' \
+             (span name=\"second\"), but 'second span' (span name=\"second\") was entered first"
+        );
+    });
+}
+
+#[test]
+fn assert_ordering_describes_a_matcher_that_was_never_entered() {
+    with_registry(|registry| {
+        let first = registry.build().with_name("first").was_entered().finalize();
+        let second = registry
+            .build()
+            .with_name("second")
+            .was_entered()
+            .finalize();
+
+        tracing::info_span!("second").in_scope(|| {});
+
+        let message = panic_message(|| registry.assert_ordering(&first, &second));
+        assert_eq!(
+            message,
+            "assertion failed: span name=\"first\" was expected to be entered before span \
+             name=\"second\", but span name=\"first\" was never entered"
+        );
+    });
+}
+
+#[test]
+fn reset_only_keeps_counts_from_after_the_reset() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .was_entered_exactly(1)
+            .finalize();
+
+        for _ in 0..3 {
+            tracing::info_span!("request").in_scope(|| {});
+        }
+        assert!(!assertion.try_assert());
+
+        assertion.reset();
+        assert_eq!(assertion.counts(), EntryCounts::default());
+
+        tracing::info_span!("request").in_scope(|| {});
+
+        assert_eq!(
+            assertion.counts(),
+            EntryCounts {
+                created: 1,
+                entered: 1,
+                exited: 1,
+                closed: 1,
+            }
+        );
+        assertion.assert();
+    });
+}
+
+#[test]
+fn reset_affects_every_assertion_sharing_the_matcher() {
+    with_registry(|registry| {
+        let a = registry
+            .build()
+            .with_name("request")
+            .was_entered()
+            .finalize();
+        let b = registry
+            .build()
+            .with_name("request")
+            .was_entered()
+            .finalize();
+
+        tracing::info_span!("request").in_scope(|| {});
+        a.reset();
+
+        assert!(!a.try_assert());
+        assert!(!b.try_assert());
+    });
+}
+
+#[test]
+fn with_no_parent_only_counts_root_spans() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("operation")
+            .with_no_parent()
+            .was_created_exactly(1)
+            .finalize();
+
+        tracing::info_span!("operation").in_scope(|| {
+            tracing::info_span!("operation").in_scope(|| {});
+        });
+
+        assert_eq!(assertion.counts().created, 1);
+        assertion.assert();
+    });
+}
+
+#[test]
+fn ignore_case_matches_names_and_targets_regardless_of_case() {
+    with_registry(|registry| {
+        let exact = registry
+            .build()
+            .with_name("request")
+            .was_created()
+            .finalize();
+        let by_name = registry
+            .build()
+            .with_name("request")
+            .ignore_case()
+            .was_created_exactly(1)
+            .finalize();
+        let by_target = registry
+            .build()
+            .with_target("MyApp::Handlers")
+            .ignore_case()
+            .was_created_exactly(1)
+            .finalize();
+
+        tracing::info_span!(target: "myapp::handlers", "Request").in_scope(|| {});
+
+        assert!(!exact.try_assert());
+        by_name.assert();
+        by_target.assert();
+    });
+}
+
+#[test]
+fn with_parent_target_matches_a_grandparent() {
+    with_registry(|registry| {
+        let by_target = registry
+            .build()
+            .with_name("query")
+            .with_parent_target("myapp::auth")
+            .was_created_exactly(1)
+            .finalize();
+        let by_target_and_name = registry
+            .build()
+            .with_name("query")
+            .with_parent_target("myapp::auth")
+            .with_parent_name("connection")
+            .was_created_exactly(1)
+            .finalize();
+        let unrelated = registry
+            .build()
+            .with_name("query")
+            .with_parent_target("myapp::billing")
+            .was_created()
+            .finalize();
+
+        tracing::info_span!(target: "myapp::auth", "login").in_scope(|| {
+            tracing::info_span!(target: "myapp::db", "connection").in_scope(|| {
+                tracing::info_span!(target: "myapp::db", "query").in_scope(|| {});
+            });
+        });
+        tracing::info_span!(target: "myapp::db", "query").in_scope(|| {});
+
+        by_target.assert();
+        by_target_and_name.assert();
+        assert!(!unrelated.try_assert());
+    });
+}
+
+#[test]
+fn was_never_over_exited_allows_exiting_a_span_entered_before_a_reset() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .was_never_over_exited()
+            .finalize();
+
+        let span = tracing::info_span!("request");
+        let entered = span.enter();
+        assertion.reset();
+        drop(entered);
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn was_never_over_exited_allows_exiting_a_span_entered_before_finalizing() {
+    with_registry(|registry| {
+        let span = tracing::info_span!("request");
+        let entered = span.enter();
+
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .was_never_over_exited()
+            .finalize();
+        drop(entered);
+        span.in_scope(|| {});
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn was_never_over_exited_allows_a_parent_entered_before_finalizing() {
+    with_registry(|registry| {
+        let span = tracing::info_span!("request");
+        let entered = span.enter();
+
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .was_never_over_exited()
+            .finalize();
+        tracing::info_span!("child").in_scope(|| {});
+        drop(entered);
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn was_never_over_exited_allows_a_parent_which_started_matching_when_entered() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .with_recorded_field("user_id")
+            .was_never_over_exited()
+            .finalize();
+
+        let span = tracing::info_span!("request", user_id = tracing::field::Empty);
+        let entered = span.enter();
+        span.record("user_id", 42);
+        tracing::info_span!("child").in_scope(|| {});
+        drop(entered);
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn was_never_over_exited_catches_an_instance_exited_while_not_entered() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .was_never_over_exited()
+            .finalize();
+
+        let span = tracing::info_span!("request");
+        span.in_scope(|| {});
+        let id = span.id().expect("span should be enabled");
+        tracing::dispatcher::get_default(|dispatch| dispatch.exit(&id));
+
+        assert!(!assertion.try_assert());
+    });
+}
+
+#[cfg(feature = "backscan")]
+#[test]
+fn was_never_over_exited_allows_exiting_a_backscanned_span() {
+    with_registry(|registry| {
+        let span = tracing::info_span!("request");
+        let entered = span.enter();
+
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .was_never_over_exited()
+            .finalize_with_backscan();
+        drop(entered);
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn layers_sharing_a_registry_in_one_subscriber_count_spans_once() {
+    let registry = AssertionRegistry::default();
+    let subscriber = Registry::default()
+        .with(AssertionsLayer::new(&registry))
+        .with(AssertionsLayer::new(&registry));
+
+    let assertion = registry
+        .build()
+        .with_name("request")
+        .was_created_exactly(1)
+        .was_entered_exactly(1)
+        .was_exited_exactly(1)
+        .was_closed_exactly(1)
+        .emitted_event_at_least(1)
+        .finalize();
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("request").in_scope(|| tracing::info!("handling"));
+    });
+
+    assertion.assert();
+    assert_eq!(assertion.counts().created, 1);
+}
+
+#[test]
+fn layers_sharing_a_registry_across_subscribers_accumulate_counts() {
+    let registry = AssertionRegistry::default();
+    let assertion = registry
+        .build()
+        .with_name("request")
+        .was_created_exactly(2)
+        .was_closed_exactly(2)
+        .finalize();
+
+    for _ in 0..2 {
+        let subscriber = Registry::default().with(AssertionsLayer::new(&registry));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("request").in_scope(|| {});
+        });
+    }
+
+    assertion.assert();
+}
+
+#[test]
+fn layers_from_different_registries_can_share_a_subscriber() {
+    let first = AssertionRegistry::default();
+    let second = AssertionRegistry::default();
+    let subscriber = Registry::default()
+        .with(AssertionsLayer::new(&first))
+        .with(AssertionsLayer::new(&second));
+
+    let in_first = first
+        .build()
+        .with_name("request")
+        .on_current_thread()
+        .was_created_exactly(1)
+        .finalize();
+    let in_second = second
+        .build()
+        .with_name("request")
+        .on_current_thread()
+        .was_created_exactly(1)
+        .finalize();
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("request").in_scope(|| {});
+    });
+
+    in_first.assert();
+    in_second.assert();
+}
+
+#[test]
+fn with_recorded_field_ignores_fields_which_are_only_declared() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .with_recorded_field("status")
+            .was_entered_exactly(1)
+            .finalize();
+
+        let declared = tracing::info_span!("request", status = tracing::field::Empty);
+        declared.in_scope(|| {});
+
+        let recorded = tracing::info_span!("request", status = tracing::field::Empty);
+        recorded.record("status", "ok");
+        recorded.in_scope(|| {});
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn assert_span_supports_every_matcher_and_criterion() {
+    with_registry(|registry| {
+        let bare = crate::assert_span!(
+            registry,
+            name = "query",
+            target = "myapp::db",
+            parent_name = "request",
+            field = "table",
+            created,
+            entered,
+            exited,
+            closed
+        );
+        let exactly = crate::assert_span!(
+            registry,
+            name = "query",
+            created == 2,
+            entered == 2,
+            exited == 2,
+            closed == 2
+        );
+        let at_least = crate::assert_span!(
+            registry,
+            name = "query",
+            created >= 1,
+            entered >= 1,
+            exited >= 1,
+            closed >= 1,
+        );
+        let unmatched =
+            crate::assert_span!(registry, name = "query", parent_name = "batch", created);
+
+        tracing::info_span!("request").in_scope(|| {
+            tracing::info_span!(target: "myapp::db", "query", table = "users").in_scope(|| {});
+        });
+        tracing::info_span!("query").in_scope(|| {});
+
+        bare.assert();
+        exactly.assert();
+        at_least.assert();
+        assert!(!unmatched.try_assert());
+    });
+}
+
+#[test]
+fn reused_span_ids_are_tracked_as_new_instances() {
+    let registry = AssertionRegistry::default();
+    let assertion = registry
+        .build()
+        .with_name("request")
+        .distinct_entered_exactly(100)
+        .every_instance_entered()
+        .every_instance_entered_at_most_once()
+        .was_never_reentered()
+        .was_never_over_exited()
+        .finalize();
+
+    let mut matcher = SpanMatcher::default();
+    matcher.set_name("request".to_string());
+    let entry = registry
+        .state()
+        .get_entry_state(&matcher)
+        .expect("entry should exist");
+
+    // The registry only reuses the slot of a closed span, with a new generation, so the layer
+    // practically never sees the same ID twice: this drives the state directly, as the layer would
+    // if it did.
+    let id = Id::from_u64(1);
+    for _ in 0..100 {
+        entry.track_created(&id, "request");
+        entry.track_entered(&id, "request");
+        entry.track_exited(&id, "request");
+        entry.track_closed(&id, "request");
+        registry.state().forget_instance(&id);
+    }
+
+    assertion.assert();
+    assert_eq!(
+        assertion.lifecycle_trace(id.into_u64()),
+        vec![Stage::Created, Stage::Entered, Stage::Exited, Stage::Closed]
+    );
+}
+
+#[test]
+fn many_closed_spans_are_each_tracked_as_one_instance() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .distinct_entered_exactly(100)
+            .every_instance_entered_at_most_once()
+            .was_never_reentered()
+            .finalize();
+
+        for _ in 0..100 {
+            tracing::info_span!("request").in_scope(|| {});
+        }
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn with_field_typed_catches_a_field_changing_type() {
+    with_registry(|registry| {
+        let numeric = registry
+            .build()
+            .with_name("request")
+            .with_field_typed("latency", FieldType::I64)
+            .was_created_exactly(1)
+            .finalize();
+        let textual = registry
+            .build()
+            .with_name("request")
+            .with_field_typed("latency", FieldType::Str)
+            .was_created_exactly(1)
+            .finalize();
+
+        let _before = tracing::info_span!("request", latency = -5i64);
+        numeric.assert();
+        assert!(!textual.try_assert());
+
+        // A span with the field recorded as a string no longer counts towards the numeric one.
+        let _after = tracing::info_span!("request", latency = "5ms");
+        numeric.assert();
+        textual.assert();
+    });
+}
+
+#[test]
+fn with_parent_name_requires_every_named_ancestor() {
+    with_registry(|registry| {
+        let both = registry
+            .build()
+            .with_name("query")
+            .with_parent_name("transaction")
+            .with_parent_name("request")
+            .was_created_exactly(1)
+            .finalize();
+
+        tracing::info_span!("request").in_scope(|| {
+            tracing::info_span!("transaction").in_scope(|| {
+                tracing::info_span!("query").in_scope(|| {});
+            });
+            tracing::info_span!("query").in_scope(|| {});
+        });
+        tracing::info_span!("transaction").in_scope(|| {
+            tracing::info_span!("query").in_scope(|| {});
+        });
+
+        both.assert();
+    });
+}
+
+#[test]
+fn fail_fast_panics_when_a_forbidden_span_is_created() {
+    with_registry(|registry| {
+        let _forbidden = registry
+            .build()
+            .with_name("forbidden")
+            .was_not_created()
+            .fail_fast()
+            .finalize();
+
+        let message = panic_message(|| {
+            let _span = tracing::info_span!("forbidden");
+        });
+        assert!(
+            message.starts_with("assertion failed: span name=\"forbidden\" was created"),
+            "unexpected panic message: {}",
+            message
+        );
+    });
+}
+
+#[test]
+fn fail_fast_stops_once_the_assertion_is_dropped() {
+    with_registry(|registry| {
+        let forbidden = registry
+            .build()
+            .with_name("forbidden")
+            .was_not_created()
+            .fail_fast()
+            .finalize();
+        let shared = registry
+            .build()
+            .with_name("forbidden")
+            .was_created_exactly(1)
+            .finalize();
+
+        drop(forbidden);
+        let _span = tracing::info_span!("forbidden");
+
+        shared.assert();
+    });
+}
+
+#[test]
+fn was_current_requires_the_span_to_be_innermost() {
+    with_registry(|registry| {
+        let query = tracing::info_span!("db_query");
+        let _outer = query.enter();
+        let request = tracing::info_span!("request").entered();
+
+        let current = registry
+            .build()
+            .with_name("db_query")
+            .was_current()
+            .finalize();
+        let entered = registry
+            .build()
+            .with_name("db_query")
+            .was_entered()
+            .finalize();
+
+        // Entering a span that's already further up the stack doesn't make it current again.
+        query.in_scope(|| {});
+        entered.assert();
+        assert!(!current.try_assert());
+
+        drop(request);
+        tracing::info_span!("db_query").in_scope(|| {});
+        current.assert();
+    });
+}
+
+#[test]
+fn spans_are_tracked_for_every_matching_span_matcher() {
+    with_registry(|registry| {
+        let by_name = registry
+            .build()
+            .with_name("query")
+            .was_created_exactly(1)
+            .was_entered_exactly(1)
+            .was_closed_exactly(1)
+            .finalize();
+        let by_name_and_target = registry
+            .build()
+            .with_name("query")
+            .with_target("myapp::db")
+            .was_created_exactly(1)
+            .was_entered_exactly(1)
+            .was_closed_exactly(1)
+            .finalize();
+
+        tracing::info_span!(target: "myapp::db", "query").in_scope(|| {});
+
+        by_name.assert();
+        by_name_and_target.assert();
+    });
+}
+
+#[test]
+fn field_recorded_at_least_only_counts_values_recorded_after_creation() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .field_recorded_at_least("status", 2)
+            .finalize();
+
+        let span = tracing::info_span!("request", status = "pending");
+        span.record("status", "running");
+        assert!(!assertion.try_assert());
+
+        span.record("status", "done");
+        assertion.assert();
+    });
+}
+
+#[test]
+fn reset_clears_reentered_spans() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .was_never_reentered()
+            .finalize();
+
+        let span = tracing::info_span!("request");
+        span.in_scope(|| span.in_scope(|| {}));
+        assert!(!assertion.try_assert());
+
+        assertion.reset();
+        assertion.assert();
+
+        // The same span instance can be counted as reentered again after a reset.
+        span.in_scope(|| span.in_scope(|| {}));
+        assert!(!assertion.try_assert());
+    });
+}
+
+#[test]
+fn with_location_only_matches_spans_from_that_call_site() {
+    with_registry(|registry| {
+        let (line, span) = (line!(), tracing::info_span!("request"));
+        let elsewhere = tracing::info_span!("request");
+
+        let at_location = registry
+            .build()
+            .with_name("request")
+            .with_location(file!(), line)
+            .was_entered_exactly(1)
+            .finalize();
+        let at_other_line = registry
+            .build()
+            .with_location(file!(), line + 100)
+            .was_entered()
+            .finalize();
+
+        span.in_scope(|| {});
+        elsewhere.in_scope(|| {});
+
+        at_location.assert();
+        assert!(!at_other_line.try_assert());
+    });
+}
+
+#[test]
+fn quick_check_only_finds_registered_span_matchers() {
+    with_registry(|registry| {
+        let _observer = registry
+            .build()
+            .with_target("myapp::db")
+            .finalize_observer();
+
+        tracing::info_span!(target: "myapp::db", "query").in_scope(|| {});
+
+        let counts = registry
+            .quick_check(|builder| builder.with_target("myapp::db"))
+            .expect("span matcher should be registered");
+        assert_eq!(counts.entered, 1);
+        assert_eq!(
+            registry.quick_check(|builder| builder.with_target("myapp::cache")),
+            None
+        );
+
+        // Predicates are compared by identity, so an identical-looking predicate is still a
+        // different span matcher.
+        let _with_predicate = registry
+            .build()
+            .with_predicate(|span| span.name() == "query")
+            .finalize_observer();
+        assert_eq!(
+            registry.quick_check(|builder| builder.with_predicate(|span| span.name() == "query")),
+            None
+        );
+    });
+}
+
+#[test]
+fn with_span_field_alone_only_counts_spans_declaring_the_field() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_span_field("user_id")
+            .was_created_exactly(1)
+            .finalize();
+        let with_name = registry
+            .build()
+            .with_name("request")
+            .with_span_field("user_id")
+            .was_created_exactly(1)
+            .finalize();
+
+        let _without = tracing::info_span!("request");
+        let _with = tracing::info_span!("request", user_id = 42);
+
+        assertion.assert();
+        with_name.assert();
+    });
+}
+
+#[test]
+fn had_depth_exactly_fails_for_a_span_nested_too_deeply() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("query")
+            .had_depth_exactly(1)
+            .finalize();
+
+        tracing::info_span!("request").in_scope(|| {
+            tracing::info_span!("query").in_scope(|| {});
+        });
+        assertion.assert();
+
+        tracing::info_span!("request").in_scope(|| {
+            tracing::info_span!("transaction").in_scope(|| {
+                tracing::info_span!("query").in_scope(|| {});
+            });
+        });
+        assert!(!assertion.try_assert());
+    });
+}
+
+#[test]
+fn with_field_in_range_matches_integer_fields() {
+    with_registry(|registry| {
+        let attempts = registry
+            .build()
+            .with_name("request")
+            .with_field_in_range("attempt", 1..=3)
+            .was_created_exactly(2)
+            .finalize();
+
+        let _signed = tracing::info_span!("request", attempt = 1i64);
+        let _unsigned = tracing::info_span!("request", attempt = 3u64);
+        let _too_many = tracing::info_span!("request", attempt = 4);
+        let _negative = tracing::info_span!("request", attempt = -1);
+        let _textual = tracing::info_span!("request", attempt = "2");
+        let _unrecorded = tracing::info_span!("request", attempt = tracing::field::Empty);
+
+        attempts.assert();
+    });
+}
+
+#[test]
+fn with_field_in_range_compares_large_integers_exactly() {
+    with_registry(|registry| {
+        // Both of these would round to the same floating-point number as their neighbours.
+        let unsigned = registry
+            .build()
+            .with_name("request")
+            .with_field_in_range("offset", u64::MAX..=u64::MAX)
+            .was_created_exactly(1)
+            .finalize();
+        let signed = registry
+            .build()
+            .with_name("request")
+            .with_field_in_range("delta", i64::MIN..=(i64::MIN + 1))
+            .was_created_exactly(1)
+            .finalize();
+
+        let _max = tracing::info_span!("request", offset = u64::MAX);
+        let _below_max = tracing::info_span!("request", offset = u64::MAX - 1);
+        let _min = tracing::info_span!("request", delta = i64::MIN);
+        let _above_range = tracing::info_span!("request", delta = i64::MIN + 2);
+
+        unsigned.assert();
+        signed.assert();
+    });
+}
+
+#[test]
+fn with_field_in_range_matches_float_fields() {
+    with_registry(|registry| {
+        let ratio = registry
+            .build()
+            .with_name("request")
+            .with_field_in_range("ratio", 0.0..=0.5)
+            .was_created_exactly(2)
+            .finalize();
+
+        let _float = tracing::info_span!("request", ratio = 0.25);
+        let _integer = tracing::info_span!("request", ratio = 0u64);
+        let _too_high = tracing::info_span!("request", ratio = 0.75);
+        let _nan = tracing::info_span!("request", ratio = f64::NAN);
+
+        ratio.assert();
+    });
+}
+
+#[test]
+fn assert_same_instance_passes_for_one_span_matching_both() {
+    with_registry(|registry| {
+        let by_name = registry
+            .build()
+            .with_name("request")
+            .was_created()
+            .finalize();
+        let by_field = registry
+            .build()
+            .with_span_field("user_id")
+            .was_created()
+            .finalize();
+
+        let _other = tracing::info_span!("request");
+        let _both = tracing::info_span!("request", user_id = 1);
+
+        registry.assert_same_instance(&by_name, &by_field);
+    });
+}
+
+#[test]
+fn assert_same_instance_fails_for_separate_spans_matching_each() {
+    with_registry(|registry| {
+        let by_name = registry
+            .build()
+            .with_name("request")
+            .was_created()
+            .finalize();
+        let by_field = registry
+            .build()
+            .with_span_field("user_id")
+            .was_created()
+            .describe("user span")
+            .finalize();
+
+        let _named = tracing::info_span!("request");
+        let _with_field = tracing::info_span!("response", user_id = 1);
+
+        let message = panic_message(|| registry.assert_same_instance(&by_name, &by_field));
+        assert_eq!(
+            message,
+            "assertion failed: span name=\"request\" and 'user span' (span field:user_id) never \
+             matched the same span, matched 1 and 1 span(s) respectively"
+        );
+    });
+}
+
+#[test]
+fn recorded_values_only_keeps_the_most_recent_values() {
+    with_registry(|registry| {
+        let assertion = registry.build().with_name("request").finalize_observer();
+
+        let total = MAX_RECORDED_VALUES as u64 + 100;
+        for attempt in 0..total {
+            let _span = tracing::info_span!("request", attempt);
+        }
+
+        let values = assertion.recorded_values("attempt");
+        assert_eq!(values.len(), MAX_RECORDED_VALUES);
+        assert_eq!(values.first(), Some(&FieldValueSnapshot::U64(100)));
+        assert_eq!(values.last(), Some(&FieldValueSnapshot::U64(total - 1)));
+    });
+}
+
+#[test]
+fn matched_span_ids_only_keeps_the_most_recent_ids() {
+    with_registry(|registry| {
+        let assertion = registry.build().with_name("request").finalize_observer();
+
+        let mut last_id = None;
+        for _ in 0..MAX_MATCHED_IDS + 100 {
+            let span = tracing::info_span!("request");
+            last_id = span.id().map(|id| id.into_u64());
+        }
+
+        let ids = assertion.matched_span_ids();
+        assert_eq!(ids.len(), MAX_MATCHED_IDS);
+        assert_eq!(ids.last().copied(), last_id);
+    });
+}
+
+#[test]
+fn events_are_counted_for_every_matching_span_in_scope() {
+    with_registry(|registry| {
+        let request = registry
+            .build()
+            .with_name("request")
+            .emitted_event_at_least(3)
+            .emitted_event_with_level(Level::ERROR)
+            .finalize();
+        let warned = registry
+            .build()
+            .with_name("request")
+            .emitted_event_with_level(Level::WARN)
+            .finalize();
+        let child = registry
+            .build()
+            .with_name("query")
+            .emitted_event_at_least(1)
+            .finalize();
+
+        // Events outside of any span have nothing to be attributed to.
+        tracing::error!("outside");
+        tracing::warn!("outside");
+        assert!(!request.try_assert());
+
+        tracing::info_span!("request").in_scope(|| {
+            tracing::info!("handling");
+            assert!(!request.try_assert());
+
+            // Events in a child span count towards the parent as well.
+            tracing::info_span!("query").in_scope(|| tracing::error!("failed"));
+            tracing::debug!("done");
+        });
+
+        request.assert();
+        child.assert();
+        assert!(!warned.try_assert());
+    });
+}
+
+#[test]
+fn events_with_an_explicit_parent_are_counted_for_that_parent() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .emitted_event_at_least(1)
+            .finalize();
+        let twice = registry
+            .build()
+            .with_name("request")
+            .emitted_event_at_least(2)
+            .finalize();
+
+        let span = tracing::info_span!("request");
+        tracing::info!(parent: &span, "handling");
+        tracing::info_span!("request").in_scope(|| tracing::info!(parent: None, "detached"));
+
+        assertion.assert();
+        assert!(!twice.try_assert());
+    });
+}
+
+#[test]
+fn total_duration_only_counts_the_outermost_interval_of_reentered_spans() {
+    with_registry(|registry| {
+        // Counting the reentered interval as well would take the total to well over 200ms.
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .total_duration_at_least(Duration::from_millis(110))
+            .total_duration_at_most(Duration::from_millis(200))
+            .finalize();
+
+        let span = tracing::info_span!("request");
+        span.in_scope(|| {
+            thread::sleep(Duration::from_millis(10));
+            span.in_scope(|| thread::sleep(Duration::from_millis(100)));
+        });
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn busy_and_idle_durations_split_the_lifetime_of_closed_spans() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .busy_duration_at_most(Duration::from_millis(200))
+            .finalize();
+
+        let start = std::time::Instant::now();
+        let span = tracing::info_span!("request");
+        span.in_scope(|| thread::sleep(Duration::from_millis(20)));
+        thread::sleep(Duration::from_millis(50));
+        span.in_scope(|| thread::sleep(Duration::from_millis(20)));
+
+        // Busy time includes spans which are still open, but idle time doesn't.
+        assert!(assertion.busy_duration() >= Duration::from_millis(40));
+        assert_eq!(assertion.idle_duration(), Duration::ZERO);
+
+        drop(span);
+        let lifetime = start.elapsed();
+
+        assert!(assertion.idle_duration() >= Duration::from_millis(50));
+        assert!(assertion.busy_duration() + assertion.idle_duration() <= lifetime);
+        assertion.assert();
+    });
+}
+
+#[test]
+fn total_duration_excludes_spans_which_have_not_exited() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("request")
+            .total_duration_at_most(Duration::ZERO)
+            .finalize();
+        let at_least = registry
+            .build()
+            .with_name("request")
+            .total_duration_at_least(Duration::from_millis(10))
+            .finalize();
+
+        let span = tracing::info_span!("request");
+        let entered = span.enter();
+        thread::sleep(Duration::from_millis(10));
+        assertion.assert();
+        assert!(!at_least.try_assert());
+
+        drop(entered);
+        assert!(!assertion.try_assert());
+        at_least.assert();
+    });
+}
+
+#[test]
+fn with_no_parent_can_be_the_first_span_matcher() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_no_parent()
+            .was_created_exactly(1)
+            .finalize();
+
+        tracing::info_span!("request").in_scope(|| {
+            let _child = tracing::info_span!("query");
+        });
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn ignore_case_can_come_before_other_span_matchers() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .ignore_case()
+            .with_name("request")
+            .was_created_exactly(1)
+            .finalize();
+
+        let _span = tracing::info_span!("REQUEST");
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn with_parent_target_can_be_the_first_span_matcher() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_parent_target("myapp::auth")
+            .was_created_exactly(1)
+            .finalize();
+
+        tracing::info_span!(target: "myapp::auth", "login").in_scope(|| {
+            let _child = tracing::info_span!("query");
+        });
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn on_current_thread_can_be_the_first_span_matcher() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .on_current_thread()
+            .was_created_exactly(1)
+            .finalize();
+
+        let _here = tracing::info_span!("request");
+        let dispatch = tracing::dispatcher::get_default(|dispatch| dispatch.clone());
+        thread::spawn(move || {
+            tracing::dispatcher::with_default(&dispatch, || {
+                let _there = tracing::info_span!("request");
+            });
+        })
+        .join()
+        .expect("thread should not panic");
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn with_recorded_field_can_be_the_first_span_matcher() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_recorded_field("status")
+            .was_created_exactly(1)
+            .finalize();
+
+        let _declared = tracing::info_span!("request", status = tracing::field::Empty);
+        let _recorded = tracing::info_span!("request", status = "ok");
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn with_field_value_debug_can_be_the_first_span_matcher() {
+    #[derive(Debug)]
+    enum Status {
+        Pending,
+        Done,
+    }
+
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_field_value_debug("status", "Pending")
+            .was_created_exactly(1)
+            .finalize();
+
+        let _pending = tracing::info_span!("request", status = ?Status::Pending);
+        let _done = tracing::info_span!("request", status = ?Status::Done);
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn with_field_typed_can_be_the_first_span_matcher() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_field_typed("latency", FieldType::F64)
+            .was_created_exactly(1)
+            .finalize();
+
+        let _float = tracing::info_span!("request", latency = 1.5);
+        let _integer = tracing::info_span!("request", latency = 2u64);
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn with_field_in_range_can_be_the_first_span_matcher() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_field_in_range("attempt", 1..=3)
+            .was_created_exactly(1)
+            .finalize();
+
+        let _in_range = tracing::info_span!("request", attempt = 2);
+        let _out_of_range = tracing::info_span!("request", attempt = 5);
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn assertion_debug_output_is_stable() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("query")
+            .with_target("myapp::db")
+            .with_parent_name("request")
+            .with_span_field("table")
+            .was_created()
+            .was_entered_exactly(2)
+            .describe("db query")
+            .finalize();
+
+        tracing::info_span!("request").in_scope(|| {
+            tracing::info_span!(target: "myapp::db", "query", table = "users").in_scope(|| {});
+        });
+
+        assert_eq!(
+            format!("{:?}", assertion),
+            "Assertion { label: \"db query\", matcher: SpanMatcher { name: \"query\", target: \
+             \"myapp::db\", parent_names: [\"request\"], fields: [Exists(\"table\")] }, \
+             criteria: [WasCreated, EnteredExactly(2)], created: 1, entered: 1, exited: 1, closed: 1 }"
+        );
+    });
+}
+
+#[test]
+fn snapshot_keeps_matchers_differing_only_by_predicate_apart() {
+    with_registry(|registry| {
+        let small = registry
+            .build()
+            .with_name("batch")
+            .with_predicate(|span| span.name() == "batch")
+            .was_created()
+            .finalize();
+        let large = registry
+            .build()
+            .with_name("batch")
+            .with_predicate(|_| false)
+            .was_not_created()
+            .finalize();
+
+        tracing::info_span!("batch").in_scope(|| {});
+
+        let snapshot = registry.snapshot();
+        let keys = snapshot.counts().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[1], format!("{} #2", keys[0]));
+        assert_eq!(snapshot.counts()[&keys[0]].created, 1);
+        assert_eq!(snapshot.counts()[&keys[1]].created, 0);
+
+        small.assert();
+        large.assert();
+    });
+}
+
+#[test]
+fn snapshot_diff_across_registries_uses_labels() {
+    fn run(spans: usize) -> crate::RegistrySnapshot {
+        let mut snapshot = None;
+        with_registry(|registry| {
+            let _thread = registry
+                .build()
+                .on_current_thread()
+                .with_name("job")
+                .was_created()
+                .describe("jobs")
+                .finalize();
+
+            for _ in 0..spans {
+                tracing::info_span!("job").in_scope(|| {});
+            }
+
+            snapshot = Some(registry.snapshot());
+        });
+        snapshot.unwrap()
+    }
+
+    let before = run(1);
+    let after = run(3);
+    assert_eq!(before.counts().keys().collect::<Vec<_>>(), vec!["jobs"]);
+
+    let deltas = before.diff(&after);
+    assert_eq!(deltas.len(), 1);
+    assert_eq!(deltas[0].matcher, "jobs");
+    assert_eq!(deltas[0].created, 2);
+    assert_eq!(deltas[0].entered, 2);
+    assert_eq!(deltas[0].exited, 2);
+    assert_eq!(deltas[0].closed, 2);
+
+    assert!(before.diff(&before).is_empty());
+}
+
+#[test]
+fn snapshot_diff_reports_changes_within_a_registry() {
+    with_registry(|registry| {
+        let _created = registry.build().with_name("a").was_created().finalize();
+        let _other = registry.build().with_name("b").was_created().finalize();
+
+        let before = registry.snapshot();
+        tracing::info_span!("a").in_scope(|| {});
+        let after = registry.snapshot();
+
+        let deltas = before.diff(&after);
+        assert_eq!(deltas.len(), 1);
+        assert!(deltas[0].matcher.contains("\"a\""), "{}", deltas[0].matcher);
+        assert_eq!(deltas[0].created, 1);
+    });
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn report_serializes_outcomes_and_counts() {
+    with_registry(|registry| {
+        let _passing = registry
+            .build()
+            .with_name("ok")
+            .was_created()
+            .describe("ok span")
+            .finalize();
+        let _failing = registry
+            .build()
+            .with_name("missing")
+            .was_created()
+            .finalize();
+
+        tracing::info_span!("ok").in_scope(|| {});
+
+        let report = serde_json::to_value(registry.report()).unwrap();
+        let report = report.as_array().unwrap();
+        assert_eq!(report.len(), 2);
+
+        let passing = &report[0];
+        assert_eq!(passing["label"], "ok span");
+        assert_eq!(passing["passed"], true);
+        assert_eq!(passing["criteria"][0]["passed"], true);
+        assert!(passing["criteria"][0]["failure"].is_null());
+        assert_eq!(
+            passing["counts"],
+            serde_json::json!({ "created": 1, "entered": 1, "exited": 1, "closed": 1 })
+        );
+
+        let failing = &report[1];
+        assert!(failing["label"].is_null());
+        assert!(failing["matcher"].as_str().unwrap().contains("missing"));
+        assert_eq!(failing["passed"], false);
+        assert_eq!(failing["criteria"][0]["passed"], false);
+        assert!(failing["criteria"][0]["failure"].is_string());
+        assert_eq!(failing["counts"]["created"], 0);
+    });
+}
+
+#[cfg(feature = "install")]
+#[test]
+fn install_captures_spans_until_the_guard_is_dropped() {
+    let (registry, guard) = AssertionRegistry::install();
+    let assertion = registry
+        .build()
+        .with_name("installed")
+        .was_created_exactly(1)
+        .finalize();
+
+    tracing::info_span!("installed").in_scope(|| {});
+    drop(guard);
+    tracing::info_span!("installed").in_scope(|| {});
+
+    assertion.assert();
+}
+
+#[test]
+fn block_until_satisfied_wakes_for_spans_on_another_thread() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("job")
+            .was_closed_exactly(2)
+            .finalize();
+
+        let dispatch = tracing::dispatcher::get_default(|dispatch| dispatch.clone());
+        let worker = thread::spawn(move || {
+            tracing::dispatcher::with_default(&dispatch, || {
+                for _ in 0..2 {
+                    thread::sleep(Duration::from_millis(20));
+                    tracing::info_span!("job").in_scope(|| {});
+                }
+            });
+        });
+
+        assert!(assertion.block_until_satisfied(Duration::from_secs(10)));
+        worker.join().expect("thread should not panic");
+    });
+}
+
+#[test]
+fn block_until_satisfied_gives_up_after_the_timeout() {
+    with_registry(|registry| {
+        let assertion = registry.build().with_name("job").was_created().finalize();
+
+        let timeout = Duration::from_millis(50);
+        let start = std::time::Instant::now();
+        assert!(!assertion.block_until_satisfied(timeout));
+        assert!(start.elapsed() >= timeout);
+    });
+}
+
+#[test]
+fn block_until_satisfied_wakes_when_a_reset_satisfies_the_criteria() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("job")
+            .was_not_entered()
+            .finalize();
+
+        tracing::info_span!("job").in_scope(|| {});
+        assert!(!assertion.try_assert());
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(20));
+                assertion.reset();
+            });
+
+            // The criteria are checked one last time at the deadline, so the wait has to finish
+            // well before then to show that the reset woke it.
+            let start = std::time::Instant::now();
+            assert!(assertion.block_until_satisfied(Duration::from_secs(10)));
+            assert!(start.elapsed() < Duration::from_secs(5));
+        });
+    });
+}
+
+#[test]
+fn window_counts_only_cover_spans_within_the_window() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_name("job")
+            .was_closed_exactly(4)
+            .finalize();
+
+        let counts = |n| EntryCounts {
+            created: n,
+            entered: n,
+            exited: n,
+            closed: n,
+        };
+        let run = |n| {
+            for _ in 0..n {
+                tracing::info_span!("job").in_scope(|| {});
+            }
+        };
+
+        run(1);
+        assert_eq!(assertion.window_counts(), EntryCounts::default());
+
+        assertion.start_window();
+        run(2);
+        assert_eq!(assertion.window_counts(), counts(2));
+
+        assertion.stop_window();
+        run(1);
+        assert_eq!(assertion.window_counts(), counts(2));
+
+        // Stopping again doesn't move the end of the window.
+        assertion.stop_window();
+        assert_eq!(assertion.window_counts(), counts(2));
+
+        // The criteria are still checked against the cumulative counts.
+        assertion.assert();
+    });
+}
+
+#[test]
+fn starting_a_window_again_discards_the_previous_window() {
+    with_registry(|registry| {
+        let assertion = registry.build().with_name("job").was_created().finalize();
+
+        assertion.start_window();
+        tracing::info_span!("job").in_scope(|| {});
+        assertion.stop_window();
+
+        assertion.start_window();
+        assert_eq!(assertion.window_counts(), EntryCounts::default());
+
+        tracing::info_span!("job").in_scope(|| {});
+        assert_eq!(assertion.window_counts().created, 1);
+    });
+}
+
+#[test]
+fn window_counts_never_go_below_zero_after_a_reset() {
+    with_registry(|registry| {
+        let assertion = registry.build().with_name("job").was_created().finalize();
+
+        tracing::info_span!("job").in_scope(|| {});
+        assertion.start_window();
+        tracing::info_span!("job").in_scope(|| {});
+        assertion.reset();
+
+        assert_eq!(assertion.window_counts(), EntryCounts::default());
+
+        // Starting a new window after the reset counts from the reset state.
+        assertion.start_window();
+        tracing::info_span!("job").in_scope(|| {});
+        assert_eq!(assertion.window_counts().created, 1);
+    });
+}
+
+#[test]
+fn was_not_created_failure_describes_the_first_offending_span() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_target("myapp::db")
+            .was_not_created()
+            .finalize();
+
+        let _first = tracing::info_span!(target: "myapp::db", "query", table = "users");
+        let _second = tracing::info_span!(target: "myapp::db", "insert");
+
+        let message = panic_message(|| assertion.assert());
+        assert!(
+            message.ends_with(
+                "was created 2 times, expected never, first created: name=\"query\" \
+                 target=\"myapp::db\" fields=[table]"
+            ),
+            "unexpected panic message: {}",
+            message
+        );
+    });
+}
+
+#[test]
+fn was_not_entered_failure_describes_the_first_offending_span() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_target("myapp::db")
+            .was_not_entered()
+            .finalize();
+
+        let _created = tracing::info_span!(target: "myapp::db", "idle");
+        tracing::info_span!(target: "myapp::db", "query").in_scope(|| {});
+
+        let message = panic_message(|| assertion.assert());
+        assert!(
+            message.ends_with(
+                "was entered 1 time, expected never, first entered: name=\"query\" \
+                 target=\"myapp::db\""
+            ),
+            "unexpected panic message: {}",
+            message
+        );
+    });
+}
+
+#[test]
+fn offending_spans_are_forgotten_on_reset() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_target("myapp::db")
+            .was_not_created()
+            .finalize();
+
+        let _before = tracing::info_span!(target: "myapp::db", "before");
+        assertion.reset();
+        let _after = tracing::info_span!(target: "myapp::db", "after");
+
+        let message = panic_message(|| assertion.assert());
+        assert!(
+            message.ends_with("first created: name=\"after\" target=\"myapp::db\""),
+            "unexpected panic message: {}",
+            message
+        );
+    });
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn with_target_regex_matches_anywhere_unless_anchored() {
+    with_registry(|registry| {
+        let unanchored = registry
+            .build()
+            .with_target_regex("db")
+            .was_created_exactly(3)
+            .finalize();
+        let anchored = registry
+            .build()
+            .with_target_regex("^myapp::(db|cache)$")
+            .was_created_exactly(2)
+            .finalize();
+        let case_insensitive = registry
+            .build()
+            .with_target_regex("(?i)^MYAPP::DB$")
+            .was_created_exactly(1)
+            .finalize();
+
+        let _db = tracing::info_span!(target: "myapp::db", "query");
+        let _cache = tracing::info_span!(target: "myapp::cache", "get");
+        let _nested = tracing::info_span!(target: "myapp::db::pool", "checkout");
+        let _other = tracing::info_span!(target: "otherdb", "query");
+
+        unanchored.assert();
+        anchored.assert();
+        case_insensitive.assert();
+    });
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn with_target_regex_panics_on_an_invalid_pattern() {
+    let registry = AssertionRegistry::default();
+    let message = panic_message(|| {
+        let _ = registry.build().with_target_regex("myapp::(db");
+    });
+    assert!(
+        message.starts_with("target regex must be valid"),
+        "unexpected panic message: {}",
+        message
+    );
+}
+
+#[test]
+fn with_follows_from_name_matches_links_made_after_creation() {
+    with_registry(|registry| {
+        let linked = registry
+            .build()
+            .with_follows_from_name("producer")
+            .was_entered_exactly(1)
+            .finalize();
+        let consumers = registry
+            .build()
+            .with_name("consumer")
+            .with_follows_from_name("producer")
+            .was_exited_exactly(1)
+            .finalize();
+        let unrelated = registry
+            .build()
+            .with_follows_from_name("other")
+            .was_not_entered()
+            .finalize();
+
+        let producer = tracing::info_span!("producer");
+        let consumer = tracing::info_span!("consumer");
+        consumer.follows_from(&producer);
+        consumer.in_scope(|| {});
+
+        // The link didn't exist when the span was created, so the creation wasn't counted.
+        assert_eq!(
+            linked.counts(),
+            EntryCounts {
+                created: 0,
+                entered: 1,
+                exited: 1,
+                closed: 0,
+            }
+        );
+        linked.assert();
+        consumers.assert();
+        unrelated.assert();
+    });
+}
+
+#[test]
+fn with_follows_from_name_ignores_links_to_closed_spans() {
+    with_registry(|registry| {
+        let assertion = registry
+            .build()
+            .with_follows_from_name("producer")
+            .was_not_entered()
+            .finalize();
+
+        let producer = tracing::info_span!("producer");
+        let producer_id = producer.id().expect("span should be enabled");
+        drop(producer);
+
+        let consumer = tracing::info_span!("consumer");
+        consumer.follows_from(producer_id);
+        consumer.in_scope(|| {});
+
+        assertion.assert();
+    });
+}
+
+#[test]
+fn had_lifecycle_sequence_never_matches_a_truncated_trace() {
+    with_registry(|registry| {
+        let mut prefix = vec![Stage::Created];
+        for _ in 0..31 {
+            prefix.extend([Stage::Entered, Stage::Exited]);
+        }
+        prefix.push(Stage::Entered);
+        assert_eq!(prefix.len(), 64);
+
+        let assertion = registry
+            .build()
+            .with_name("loop")
+            .had_lifecycle_sequence(prefix.clone())
+            .finalize();
+
+        let span = tracing::info_span!("loop");
+        for _ in 0..50 {
+            span.in_scope(|| {});
+        }
+        let id = span.id().expect("span should be enabled").into_u64();
+        drop(span);
+
+        // Only the first 64 of the 102 stages were recorded, which are exactly the expected ones.
+        assert_eq!(assertion.lifecycle_trace(id), prefix);
+        assert!(!assertion.try_assert());
+    });
+}
@@ -0,0 +1,218 @@
+use std::{cmp::Ordering, collections::HashMap, convert::TryFrom, fmt};
+
+use tracing::field::{Field, Visit};
+
+/// A snapshot of a value recorded on a span field.
+///
+/// Primitive values retain the type they were recorded with, while all other values -- such as
+/// those recorded with `?value` -- are captured using their `Debug` representation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValueSnapshot {
+    /// A signed integer value.
+    I64(i64),
+
+    /// An unsigned integer value.
+    U64(u64),
+
+    /// A floating-point value.
+    F64(f64),
+
+    /// A boolean value.
+    Bool(bool),
+
+    /// A string value.
+    Str(String),
+
+    /// The `Debug` representation of any other value.
+    Debug(String),
+}
+
+/// The type a span field value was recorded with.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FieldType {
+    /// A signed integer value.
+    I64,
+
+    /// An unsigned integer value.
+    U64,
+
+    /// A floating-point value.
+    F64,
+
+    /// A boolean value.
+    Bool,
+
+    /// A string value.
+    Str,
+
+    /// Any other value, recorded using its `Debug` representation.
+    Debug,
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::I64 => write!(f, "i64"),
+            FieldType::U64 => write!(f, "u64"),
+            FieldType::F64 => write!(f, "f64"),
+            FieldType::Bool => write!(f, "bool"),
+            FieldType::Str => write!(f, "str"),
+            FieldType::Debug => write!(f, "debug"),
+        }
+    }
+}
+
+impl FieldValueSnapshot {
+    /// Gets the type the value was recorded with.
+    pub fn field_type(&self) -> FieldType {
+        match self {
+            FieldValueSnapshot::I64(_) => FieldType::I64,
+            FieldValueSnapshot::U64(_) => FieldType::U64,
+            FieldValueSnapshot::F64(_) => FieldType::F64,
+            FieldValueSnapshot::Bool(_) => FieldType::Bool,
+            FieldValueSnapshot::Str(_) => FieldType::Str,
+            FieldValueSnapshot::Debug(_) => FieldType::Debug,
+        }
+    }
+
+    /// Renders the value as it would be rendered by its `Debug` implementation.
+    ///
+    /// Values captured using their `Debug` representation are returned as-is.
+    pub(crate) fn to_debug_string(&self) -> String {
+        match self {
+            FieldValueSnapshot::I64(value) => format!("{:?}", value),
+            FieldValueSnapshot::U64(value) => format!("{:?}", value),
+            FieldValueSnapshot::F64(value) => format!("{:?}", value),
+            FieldValueSnapshot::Bool(value) => format!("{:?}", value),
+            FieldValueSnapshot::Str(value) => format!("{:?}", value),
+            FieldValueSnapshot::Debug(value) => value.clone(),
+        }
+    }
+
+    /// Gets the value as a floating-point number, if it was recorded as a number.
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            FieldValueSnapshot::I64(value) => Some(*value as f64),
+            FieldValueSnapshot::U64(value) => Some(*value as f64),
+            FieldValueSnapshot::F64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Compares two numeric values.
+    ///
+    /// Integers are compared exactly, regardless of whether they were recorded as signed or
+    /// unsigned, while comparisons involving a floating-point number are made as floating-point
+    /// numbers.  Returns `None` if either value isn't a number, or if either is NaN.
+    pub(crate) fn compare_numeric(&self, other: &FieldValueSnapshot) -> Option<Ordering> {
+        match (self, other) {
+            (FieldValueSnapshot::I64(a), FieldValueSnapshot::I64(b)) => Some(a.cmp(b)),
+            (FieldValueSnapshot::U64(a), FieldValueSnapshot::U64(b)) => Some(a.cmp(b)),
+            (FieldValueSnapshot::I64(a), FieldValueSnapshot::U64(b)) => match u64::try_from(*a) {
+                Ok(a) => Some(a.cmp(b)),
+                Err(_) => Some(Ordering::Less),
+            },
+            (FieldValueSnapshot::U64(a), FieldValueSnapshot::I64(b)) => match u64::try_from(*b) {
+                Ok(b) => Some(a.cmp(&b)),
+                Err(_) => Some(Ordering::Greater),
+            },
+            (a, b) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        }
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A bound of a range of numeric field values, as given to
+/// [`with_field_in_range`][crate::AssertionBuilder::with_field_in_range].
+///
+/// This is implemented for all of the primitive integer and floating-point types, and cannot be
+/// implemented outside of this crate.
+pub trait FieldBound: sealed::Sealed {
+    #[doc(hidden)]
+    fn into_snapshot(self) -> FieldValueSnapshot;
+}
+
+macro_rules! impl_field_bound {
+    ($variant:ident($as:ty): $($ty:ty),+) => {
+        $(
+            impl sealed::Sealed for $ty {}
+
+            impl FieldBound for $ty {
+                fn into_snapshot(self) -> FieldValueSnapshot {
+                    FieldValueSnapshot::$variant(self as $as)
+                }
+            }
+        )+
+    };
+}
+
+impl_field_bound!(I64(i64): i8, i16, i32, i64, isize);
+impl_field_bound!(U64(u64): u8, u16, u32, u64, usize);
+impl_field_bound!(F64(f64): f32, f64);
+
+/// Collects the values visited from a span's attributes or a [`tracing::span::Record`].
+#[derive(Default)]
+pub(crate) struct ValueRecorder {
+    values: Vec<(&'static str, FieldValueSnapshot)>,
+}
+
+impl ValueRecorder {
+    pub fn into_values(self) -> Vec<(&'static str, FieldValueSnapshot)> {
+        self.values
+    }
+}
+
+impl Visit for ValueRecorder {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.values
+            .push((field.name(), FieldValueSnapshot::I64(value)));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.values
+            .push((field.name(), FieldValueSnapshot::U64(value)));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.values
+            .push((field.name(), FieldValueSnapshot::F64(value)));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.values
+            .push((field.name(), FieldValueSnapshot::Bool(value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.values
+            .push((field.name(), FieldValueSnapshot::Str(value.to_string())));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.values.push((
+            field.name(),
+            FieldValueSnapshot::Debug(format!("{:?}", value)),
+        ));
+    }
+}
+
+/// The most recent value recorded for each field of a span, stored in the span's extensions.
+#[derive(Default)]
+pub(crate) struct RecordedValues {
+    values: HashMap<&'static str, FieldValueSnapshot>,
+}
+
+impl RecordedValues {
+    pub fn update(&mut self, recorded: &[(&'static str, FieldValueSnapshot)]) {
+        for (field, value) in recorded {
+            self.values.insert(field, value.clone());
+        }
+    }
+
+    pub fn get(&self, field: &str) -> Option<&FieldValueSnapshot> {
+        self.values.get(field)
+    }
+}
@@ -0,0 +1,172 @@
+#![cfg(feature = "diagnostics")]
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_fluent_assertions::{AssertionRegistry, AssertionsLayer};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    Layer, Registry,
+};
+
+const TARGET: &str = "tracing_fluent_assertions::diagnostics";
+
+/// A diagnostic event, as captured by [`CaptureLayer`].
+#[derive(Debug, Default, PartialEq)]
+struct Diagnostic {
+    label: Option<String>,
+    satisfied: bool,
+    created: u64,
+    entered: u64,
+}
+
+impl Visit for Diagnostic {
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        if field.name() == "satisfied" {
+            self.satisfied = value;
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match field.name() {
+            "created" => self.created = value,
+            "entered" => self.entered = value,
+            _ => {}
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "label" {
+            self.label = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, _: &Field, _: &dyn fmt::Debug) {}
+}
+
+/// Captures every diagnostic event which reaches the subscriber.
+#[derive(Clone, Default)]
+struct CaptureLayer {
+    diagnostics: Arc<Mutex<Vec<Diagnostic>>>,
+}
+
+impl CaptureLayer {
+    fn take(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut *self.diagnostics.lock().unwrap())
+    }
+}
+
+impl<S: Subscriber> Layer<S> for CaptureLayer {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        if event.metadata().target() != TARGET {
+            return;
+        }
+
+        let mut diagnostic = Diagnostic::default();
+        event.record(&mut diagnostic);
+        self.diagnostics.lock().unwrap().push(diagnostic);
+    }
+}
+
+fn with_capture<F>(f: F)
+where
+    F: FnOnce(&AssertionRegistry, &CaptureLayer),
+{
+    let registry = AssertionRegistry::default();
+    let capture = CaptureLayer::default();
+    let subscriber = Registry::default()
+        .with(AssertionsLayer::new(&registry))
+        .with(capture.clone());
+    tracing::subscriber::with_default(subscriber, || f(&registry, &capture));
+}
+
+#[test]
+fn an_event_is_emitted_once_when_an_assertion_becomes_satisfied() {
+    with_capture(|registry, capture| {
+        let _assertion = registry
+            .build()
+            .with_name("job")
+            .was_entered()
+            .describe("job entered")
+            .finalize();
+
+        let span = tracing::info_span!("job");
+        for _ in 0..3 {
+            span.in_scope(|| {});
+        }
+        drop(span);
+
+        assert_eq!(
+            capture.take(),
+            vec![Diagnostic {
+                label: Some("job entered".to_string()),
+                satisfied: true,
+                created: 1,
+                entered: 1,
+            }]
+        );
+    });
+}
+
+#[test]
+fn an_event_is_emitted_when_an_assertion_becomes_violated() {
+    with_capture(|registry, capture| {
+        let _assertion = registry
+            .build()
+            .with_name("job")
+            .was_not_entered()
+            .finalize();
+
+        let span = tracing::info_span!("job");
+        span.in_scope(|| {});
+        span.in_scope(|| {});
+
+        let diagnostics = capture.take();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(!diagnostics[0].satisfied);
+        assert_eq!(diagnostics[0].entered, 1);
+    });
+}
+
+#[test]
+fn diagnostic_events_do_not_feed_back_into_assertions() {
+    with_capture(|registry, capture| {
+        let events = registry
+            .build()
+            .with_name("job")
+            .emitted_event_at_least(1)
+            .finalize();
+        let _entered = registry.build().with_name("job").was_entered().finalize();
+
+        // The diagnostic event for `was_entered` is emitted while the span is entered, but isn't
+        // counted towards it.
+        tracing::info_span!("job").in_scope(|| {});
+
+        assert_eq!(capture.take().len(), 1);
+        assert!(!events.try_assert());
+    });
+}
+
+#[test]
+fn diagnostics_queued_without_a_dispatcher_are_emitted_later() {
+    with_capture(|registry, capture| {
+        let _assertion = registry.build().with_name("job").was_created().finalize();
+
+        // No dispatcher is available to the layer while the span is being created, so the
+        // diagnostic event is held back until the layer is next called.
+        let span = tracing::info_span!("job");
+        assert!(capture.take().is_empty());
+
+        span.in_scope(|| {});
+        let diagnostics = capture.take();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].satisfied);
+        assert_eq!(diagnostics[0].created, 1);
+    });
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "diagnostics")]
+
+//! Re-entrant events are only delivered through a global default subscriber, as `tracing` drops
+//! events emitted while a scoped default subscriber is busy, so this needs a test binary of its
+//! own.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use tracing::{Event, Subscriber};
+use tracing_fluent_assertions::{AssertionRegistry, AssertionsLayer};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    Layer, Registry,
+};
+
+/// Responds to every diagnostic event by emitting an event of its own.
+#[derive(Clone, Default)]
+struct ReactingLayer {
+    diagnostics: Arc<AtomicUsize>,
+    reactions: Arc<AtomicUsize>,
+}
+
+impl<S: Subscriber> Layer<S> for ReactingLayer {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        if event.metadata().target() == "tracing_fluent_assertions::diagnostics" {
+            self.diagnostics.fetch_add(1, Ordering::SeqCst);
+            tracing::info!("reacting to a diagnostic event");
+        } else if event.metadata().target() == module_path!() {
+            self.reactions.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[test]
+fn emitting_through_the_same_subscriber_does_not_recurse() {
+    let registry = AssertionRegistry::default();
+    let reacting = ReactingLayer::default();
+    let subscriber = Registry::default()
+        .with(AssertionsLayer::new(&registry))
+        .with(reacting.clone());
+    tracing::subscriber::set_global_default(subscriber).expect("no global default yet");
+
+    let _entered = registry.build().with_name("job").was_entered().finalize();
+    let events = registry
+        .build()
+        .with_name("job")
+        .emitted_event_at_least(1)
+        .finalize();
+
+    // The reaction is emitted within the span, as the diagnostic event for `was_entered` is
+    // emitted once it has been entered, but it's ignored like the diagnostic event itself.
+    tracing::info_span!("job").in_scope(|| {});
+
+    assert_eq!(reacting.diagnostics.load(Ordering::SeqCst), 1);
+    assert_eq!(reacting.reactions.load(Ordering::SeqCst), 1);
+    assert!(!events.try_assert());
+}
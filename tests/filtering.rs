@@ -0,0 +1,78 @@
+use tracing_fluent_assertions::{AssertionRegistry, AssertionsLayer};
+use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Layer, Registry};
+
+/// A layer which does nothing, standing in for something like a formatting layer.
+struct NoopLayer;
+
+impl<S: tracing::Subscriber> Layer<S> for NoopLayer {}
+
+#[test]
+fn global_env_filter_hides_disabled_spans_from_the_assertions_layer() {
+    let registry = AssertionRegistry::default();
+    let subscriber = Registry::default()
+        .with(AssertionsLayer::new(&registry))
+        .with(EnvFilter::new("info"));
+
+    let debug = registry
+        .build()
+        .with_name("debug")
+        .was_not_created()
+        .finalize();
+    let info = registry.build().with_name("info").was_created().finalize();
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::debug_span!("debug").in_scope(|| {});
+        tracing::info_span!("info").in_scope(|| {});
+    });
+
+    debug.assert();
+    info.assert();
+}
+
+#[test]
+fn per_layer_env_filter_leaves_the_assertions_layer_seeing_every_span() {
+    let registry = AssertionRegistry::default();
+    let subscriber = Registry::default()
+        .with(AssertionsLayer::new(&registry))
+        .with(NoopLayer.with_filter(EnvFilter::new("info")));
+
+    let debug = registry.build().with_name("debug").was_created().finalize();
+    let info = registry.build().with_name("info").was_created().finalize();
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::debug_span!("debug").in_scope(|| {});
+        tracing::info_span!("info").in_scope(|| {});
+    });
+
+    debug.assert();
+    info.assert();
+}
+
+#[test]
+fn env_filter_on_the_assertions_layer_only_tracks_spans_it_enables() {
+    let registry = AssertionRegistry::default();
+    let subscriber = Registry::default()
+        .with(AssertionsLayer::new(&registry).with_filter(EnvFilter::new("info,sampled=off")))
+        .with(NoopLayer.with_filter(EnvFilter::new("info,sampled=off")));
+
+    let sampled_out = registry
+        .build()
+        .with_name("request")
+        .with_target("sampled")
+        .was_not_created()
+        .finalize();
+    let kept = registry
+        .build()
+        .with_name("request")
+        .with_target("kept")
+        .was_created()
+        .finalize();
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!(target: "sampled", "request").in_scope(|| {});
+        tracing::info_span!(target: "kept", "request").in_scope(|| {});
+    });
+
+    sampled_out.assert();
+    kept.assert();
+}
@@ -0,0 +1,6 @@
+#[test]
+fn assert_span_macro() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/pass/*.rs");
+    cases.compile_fail("tests/ui/fail/*.rs");
+}
@@ -0,0 +1,6 @@
+use tracing_fluent_assertions::{assert_span, AssertionRegistry};
+
+fn main() {
+    let registry = AssertionRegistry::default();
+    let _assertion = assert_span!(registry, name = "query", opened);
+}
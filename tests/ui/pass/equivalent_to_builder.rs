@@ -0,0 +1,41 @@
+use tracing_fluent_assertions::{assert_span, AssertionRegistry, AssertionsLayer};
+use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+fn main() {
+    let registry = AssertionRegistry::default();
+    let subscriber = Registry::default().with(AssertionsLayer::new(&registry));
+
+    let from_macro = assert_span!(
+        registry,
+        name = "query",
+        target = "myapp::db",
+        parent_name = "request",
+        field = "table",
+        created,
+        entered >= 1,
+        exited == 1,
+        closed
+    );
+    let from_builder = registry
+        .build()
+        .with_name("query")
+        .with_target("myapp::db")
+        .with_parent_name("request")
+        .with_span_field("table")
+        .was_created()
+        .was_entered_at_least(1)
+        .was_exited_exactly(1)
+        .was_closed()
+        .finalize();
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("request").in_scope(|| {
+            tracing::info_span!(target: "myapp::db", "query", table = "users").in_scope(|| {});
+        });
+    });
+
+    // The debug output covers the span matcher, the criteria, and the counts, so identical output
+    // means the macro built exactly the same assertion as the builder chain.
+    assert_eq!(format!("{:?}", from_macro), format!("{:?}", from_builder));
+    from_macro.assert();
+}